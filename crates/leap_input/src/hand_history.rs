@@ -0,0 +1,225 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::leap_controller_plugin::{update_bones_transforms, HandsData, HeldHands, MyHand, MyHandType};
+
+/// Records [`HandsData`] into [`HandsHistory`]. [`HeldHands`] aren't recorded, since they don't move.
+pub struct HandHistoryPlugin;
+
+impl Plugin for HandHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        // recording after the bones are updated also catches hands written by the synthetic hand
+        app.init_resource::<HandsHistory>()
+            .add_system(record_hands_history.after(update_bones_transforms));
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct HandSample {
+    /// Seconds since the startup of the app.
+    pub time: f64,
+    pub hand: MyHand,
+}
+
+/// Hands from the last [`HandsHistory::max_age`] seconds, separately for the left and the right hand.
+/// Queries take time relative to the latest recorded frame, so `0.1` means 100 ms before it.
+/// Samples of a hand are dropped when it's missing in a frame, so its path doesn't jump over the gap.
+#[derive(Resource)]
+pub struct HandsHistory {
+    pub max_age: f64,
+    samples: HashMap<MyHandType, VecDeque<HandSample>>,
+    /// Time of the latest recorded frame.
+    now: f64,
+}
+
+impl Default for HandsHistory {
+    fn default() -> Self {
+        HandsHistory {
+            max_age: 2.,
+            samples: HashMap::default(),
+            now: 0.,
+        }
+    }
+}
+
+impl HandsHistory {
+    pub fn push(&mut self, time: f64, hands: &[MyHand]) {
+        self.now = time;
+        self.samples
+            .retain(|type_, _| hands.iter().any(|hand| hand.type_ == *type_));
+
+        for hand in hands {
+            self.samples
+                .entry(hand.type_)
+                .or_default()
+                .push_back(HandSample { time, hand: *hand });
+        }
+
+        let oldest = time - self.max_age;
+        for samples in self.samples.values_mut() {
+            while matches!(samples.front(), Some(sample) if sample.time < oldest) {
+                samples.pop_front();
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Recorded samples of the hand, from the oldest.
+    pub fn samples(&self, type_: MyHandType) -> impl DoubleEndedIterator<Item = &HandSample> {
+        self.samples.get(&type_).into_iter().flatten()
+    }
+
+    pub fn latest(&self, type_: MyHandType) -> Option<&HandSample> {
+        self.samples(type_).next_back()
+    }
+
+    /// The latest sample recorded at least `seconds_ago` before the latest frame.
+    pub fn sample_at(&self, type_: MyHandType, seconds_ago: f64) -> Option<&HandSample> {
+        let time = self.now - seconds_ago;
+        self.samples(type_).rev().find(|sample| sample.time <= time)
+    }
+
+    /// Point of the hand, e.g. `|hand| hand.digits[1].tip()`, `seconds_ago` before the latest frame.
+    /// It is interpolated between the closest samples.
+    pub fn point_at(&self, type_: MyHandType, seconds_ago: f64, point: impl Fn(&MyHand) -> Vec3) -> Option<Vec3> {
+        let time = self.now - seconds_ago;
+        let samples = self.samples.get(&type_)?;
+        let after_index = samples.iter().position(|sample| sample.time >= time)?;
+        let after = &samples[after_index];
+        if after_index == 0 {
+            // older than the history, unless the time hits the oldest sample exactly
+            return (after.time == time).then(|| point(&after.hand));
+        }
+
+        let before = &samples[after_index - 1];
+        let t = ((time - before.time) / (after.time - before.time)) as f32;
+
+        Some(point(&before.hand).lerp(point(&after.hand), t))
+    }
+
+    /// Average velocity of the point over the last `window` seconds, in millimeters per second.
+    pub fn average_velocity(&self, type_: MyHandType, window: f64, point: impl Fn(&MyHand) -> Vec3) -> Option<Vec3> {
+        let window_samples = self.window(type_, window);
+        let (first, last) = (window_samples.first()?, window_samples.last()?);
+        let duration = last.time - first.time;
+        if duration <= 0. {
+            return None;
+        }
+
+        Some((point(&last.hand) - point(&first.hand)) / duration as f32)
+    }
+
+    /// Distance traveled by the point over the last `window` seconds, in millimeters.
+    pub fn path_length(&self, type_: MyHandType, window: f64, point: impl Fn(&MyHand) -> Vec3) -> f32 {
+        self.window(type_, window)
+            .windows(2)
+            .map(|pair| point(&pair[0].hand).distance(point(&pair[1].hand)))
+            .sum()
+    }
+
+    fn window(&self, type_: MyHandType, window: f64) -> Vec<&HandSample> {
+        let oldest = self.now - window;
+        self.samples(type_).filter(|sample| sample.time >= oldest).collect()
+    }
+}
+
+fn record_hands_history(
+    time: Res<Time>,
+    hands_data: Res<HandsData>,
+    held_hands: Res<HeldHands>,
+    mut history: ResMut<HandsHistory>,
+) {
+    if !hands_data.is_changed() {
+        return;
+    }
+
+    let tracked_hands = hands_data
+        .hands
+        .iter()
+        .filter(|hand| !held_hands.is_held(hand.type_))
+        .copied()
+        .collect::<Vec<_>>();
+    history.push(time.elapsed_seconds_f64(), &tracked_hands);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hand_at(type_: MyHandType, position: Vec3) -> MyHand {
+        let mut hand = MyHand { type_, ..default() };
+        hand.palm.position = position;
+
+        hand
+    }
+
+    fn palm(hand: &MyHand) -> Vec3 {
+        hand.palm.position
+    }
+
+    /// Right hand moving along `X` by 10 mm every 0.1 s, from `x = 0` at the time 1.
+    fn moving_hand_history() -> HandsHistory {
+        let mut history = HandsHistory::default();
+        for step in 0..=5 {
+            let position = Vec3::new(step as f32 * 10., 0., 0.);
+            history.push(1. + step as f64 * 0.1, &[hand_at(MyHandType::Right, position)]);
+        }
+
+        history
+    }
+
+    #[test]
+    fn sample_at_finds_older_sample() {
+        let history = moving_hand_history();
+
+        let sample = history.sample_at(MyHandType::Right, 0.15).unwrap();
+        assert!((sample.time - 1.3).abs() < 1e-9);
+        assert!(history.sample_at(MyHandType::Right, 1.).is_none());
+        assert!(history.sample_at(MyHandType::Left, 0.).is_none());
+    }
+
+    #[test]
+    fn point_at_interpolates_samples() {
+        let history = moving_hand_history();
+
+        let point = history.point_at(MyHandType::Right, 0.25, palm).unwrap();
+        assert!(point.abs_diff_eq(Vec3::new(25., 0., 0.), 1e-3), "{point}");
+        assert!(history.point_at(MyHandType::Right, 0.6, palm).is_none());
+    }
+
+    #[test]
+    fn velocity_and_path_length_cover_window() {
+        let history = moving_hand_history();
+
+        let velocity = history.average_velocity(MyHandType::Right, 0.2, palm).unwrap();
+        assert!(velocity.abs_diff_eq(Vec3::new(100., 0., 0.), 1e-2), "{velocity}");
+        assert!((history.path_length(MyHandType::Right, 0.2, palm) - 20.).abs() < 1e-3);
+        assert!(history.average_velocity(MyHandType::Right, 0., palm).is_none());
+    }
+
+    #[test]
+    fn old_samples_are_dropped() {
+        let mut history = moving_hand_history();
+        history.max_age = 0.25;
+        history.push(1.6, &[hand_at(MyHandType::Right, Vec3::new(60., 0., 0.))]);
+
+        assert_eq!(history.samples(MyHandType::Right).count(), 3);
+    }
+
+    #[test]
+    fn missing_hand_starts_new_path() {
+        let mut history = moving_hand_history();
+        history.push(1.6, &[hand_at(MyHandType::Left, Vec3::ZERO)]);
+        history.push(1.7, &[hand_at(MyHandType::Right, Vec3::new(500., 0., 0.))]);
+        history.push(1.8, &[hand_at(MyHandType::Right, Vec3::new(510., 0., 0.))]);
+
+        assert_eq!(history.samples(MyHandType::Right).count(), 2);
+        assert!(history.latest(MyHandType::Left).is_none());
+        assert!((history.path_length(MyHandType::Right, 1., palm) - 10.).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,320 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use crate::leap_controller_plugin::{MyBone, MyDigit, MyHand, MyHandType, MyPalm};
+
+/// Fingertip distance from the thumb tip at which the pinch strength reaches 1.
+const PINCH_TOUCH_DISTANCE: f32 = 15.;
+
+/// Fingertip distance from the thumb tip above which the pinch strength is 0.
+const PINCH_RELEASE_DISTANCE: f32 = 65.;
+
+/// Maximal angle between the distal bone and the metacarpal of an extended digit.
+const EXTENDED_MAX_ANGLE: f32 = PI / 4.;
+
+/// Bones of a single digit, in the hand space of a right hand.
+/// The hand space has the palm center in the origin, the palm facing `-Y` and fingers pointing `-Z`;
+/// the thumb is on the `-X` side.
+#[derive(Clone, Copy, Debug)]
+pub struct DigitSkeleton {
+    /// Base of the metacarpal.
+    pub base: Vec3,
+    /// Direction of the metacarpal.
+    pub direction: Vec3,
+    /// Back of the digit, i.e. the side of the nail. It's perpendicularized against `direction`.
+    pub up: Vec3,
+    /// Lengths of the metacarpal, proximal, intermediate and distal bones.
+    /// Thumb's metacarpal has zero length, like in the tracking data.
+    pub lengths: [f32; 4],
+    /// Widths of the metacarpal, proximal, intermediate and distal bones.
+    pub widths: [f32; 4],
+}
+
+/// Angles of a single digit, in radians.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DigitAngles {
+    /// Flexion of the knuckle, the middle and the last joint. Positive angles curl the digit toward the palm.
+    pub flexion: [f32; 3],
+    /// Side rotation in the knuckle. Positive angles move the digit toward the thumb side.
+    pub abduction: f32,
+}
+
+/// Angles of all digits, in the order of [`MyHand::digits`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HandPose {
+    pub digits: [DigitAngles; 5],
+}
+
+impl HandPose {
+    /// Flexion of a fully curled digit, from the knuckle to the tip.
+    pub const FINGER_MAX_FLEXION: [f32; 3] = [80. * PI / 180., 100. * PI / 180., 70. * PI / 180.];
+    pub const THUMB_MAX_FLEXION: [f32; 3] = [30. * PI / 180., 50. * PI / 180., 60. * PI / 180.];
+
+    pub fn open() -> Self {
+        HandPose::default()
+    }
+
+    /// Each digit curled by the given part (from 0 to 1) of its maximal flexion.
+    pub fn curled(curls: [f32; 5]) -> Self {
+        let mut pose = HandPose::default();
+        for (index, digit) in pose.digits.iter_mut().enumerate() {
+            let max_flexion = if index == 0 {
+                HandPose::THUMB_MAX_FLEXION
+            } else {
+                HandPose::FINGER_MAX_FLEXION
+            };
+            digit.flexion = max_flexion.map(|angle| angle * curls[index]);
+        }
+
+        pose
+    }
+
+    pub fn fist() -> Self {
+        HandPose::curled([1.; 5])
+    }
+
+    /// Thumb tip touching the index fingertip, other fingers relaxed.
+    pub fn pinch() -> Self {
+        let mut pose = HandPose::curled([0., 0.5, 0.2, 0.2, 0.2]);
+        pose.digits[0] = DigitAngles {
+            flexion: [0.48, 0.43, 0.33],
+            abduction: -0.1,
+        };
+
+        pose
+    }
+
+    /// Interpolates angles of both poses.
+    pub fn lerp(&self, other: &HandPose, t: f32) -> Self {
+        let mut pose = *self;
+        for (digit, other_digit) in pose.digits.iter_mut().zip(other.digits.iter()) {
+            for (angle, other_angle) in digit.flexion.iter_mut().zip(other_digit.flexion.iter()) {
+                *angle += (other_angle - *angle) * t;
+            }
+            digit.abduction += (other_digit.abduction - digit.abduction) * t;
+        }
+
+        pose
+    }
+}
+
+/// Dimensions of a hand, used to compute [`MyHand`] from joint angles.
+#[derive(Clone, Debug)]
+pub struct HandSkeleton {
+    /// In the order of [`MyHand::digits`].
+    pub digits: [DigitSkeleton; 5],
+    pub palm_width: f32,
+    /// Wrist position in the hand space.
+    pub wrist: Vec3,
+    pub arm_length: f32,
+    pub arm_width: f32,
+}
+
+impl Default for HandSkeleton {
+    /// Average adult hand, in millimeters.
+    fn default() -> Self {
+        let finger = |knuckle: Vec3, lengths: [f32; 3]| {
+            // metacarpals converge toward the wrist
+            let base = Vec3::new(knuckle.x * 0.4, 0., 45.);
+
+            DigitSkeleton {
+                base,
+                direction: (knuckle - base).normalize(),
+                up: Vec3::Y,
+                lengths: [base.distance(knuckle), lengths[0], lengths[1], lengths[2]],
+                widths: [18., 16., 14., 12.],
+            }
+        };
+
+        HandSkeleton {
+            digits: [
+                DigitSkeleton {
+                    base: Vec3::new(-30., -10., 25.),
+                    direction: Vec3::new(-0.6, -0.1, -0.8).normalize(),
+                    // thumb's nail faces away from the palm and the fingers
+                    up: Vec3::new(-0.7, 0.7, 0.),
+                    lengths: [0., 45., 32., 25.],
+                    widths: [0., 20., 18., 16.],
+                },
+                finger(Vec3::new(-27., 0., -30.), [40., 23., 18.]),
+                finger(Vec3::new(-8., 0., -33.), [45., 27., 19.]),
+                finger(Vec3::new(10., 0., -30.), [42., 26., 19.]),
+                finger(Vec3::new(27., 0., -22.), [33., 18., 17.]),
+            ],
+            palm_width: 85.,
+            wrist: Vec3::new(0., 0., 60.),
+            arm_length: 250.,
+            arm_width: 60.,
+        }
+    }
+}
+
+/// Rotation of a bone pointing in the `direction`, with `up` on its back side.
+/// Bones look along their negative `Z` axis, like in the tracking data.
+fn bone_rotation(direction: Vec3, up: Vec3) -> Quat {
+    let back = -direction.normalize();
+    let right = up.cross(back).normalize();
+    let up = back.cross(right);
+
+    Quat::from_mat3(&Mat3::from_cols(right, up, back))
+}
+
+impl HandSkeleton {
+    /// Computes the hand in the given pose. `transform` places the hand space in the tracking device space;
+    /// left hands are mirrored along the `X` axis of the hand space.
+    pub fn solve(&self, type_: MyHandType, pose: &HandPose, transform: &Transform) -> MyHand {
+        let mirror = |v: Vec3| match type_ {
+            MyHandType::Left => Vec3::new(-v.x, v.y, v.z),
+            MyHandType::Right => v,
+        };
+        let to_point = |v: Vec3| transform.transform_point(mirror(v));
+        let to_direction = |v: Vec3| transform.rotation * mirror(v);
+        let to_bone = |prev_joint: Vec3, next_joint: Vec3, rotation: Quat, width: f32| MyBone {
+            prev_joint: to_point(prev_joint),
+            next_joint: to_point(next_joint),
+            width,
+            rotation: bone_rotation(to_direction(rotation * Vec3::NEG_Z), to_direction(rotation * Vec3::Y)),
+        };
+
+        let mut digits = [MyDigit::default(); 5];
+        for (index, (skeleton, angles)) in self.digits.iter().zip(pose.digits.iter()).enumerate() {
+            let mut rotation = bone_rotation(skeleton.direction, skeleton.up);
+            let mut joint = skeleton.base;
+            let mut bones = [MyBone::default(); 4];
+
+            for (bone_index, bone) in bones.iter_mut().enumerate() {
+                if bone_index > 0 {
+                    if bone_index == 1 {
+                        rotation *= Quat::from_rotation_y(angles.abduction);
+                    }
+                    // the palm is on the negative `Y` side of the bone
+                    rotation *= Quat::from_rotation_x(-angles.flexion[bone_index - 1]);
+                }
+
+                let next_joint = joint + rotation * Vec3::NEG_Z * skeleton.lengths[bone_index];
+                *bone = to_bone(joint, next_joint, rotation, skeleton.widths[bone_index]);
+                joint = next_joint;
+            }
+
+            // thumb's metacarpal has no direction
+            let reference = if index == 0 { &bones[1] } else { &bones[0] };
+            let reference_direction = (reference.next_joint - reference.prev_joint).normalize_or_zero();
+            let distal_direction = (bones[3].next_joint - bones[3].prev_joint).normalize_or_zero();
+
+            digits[index] = MyDigit {
+                metacarpal: bones[0],
+                proximal: bones[1],
+                intermediate: bones[2],
+                distal: bones[3],
+                is_extended: reference_direction.angle_between(distal_direction) < EXTENDED_MAX_ANGLE,
+            };
+        }
+
+        let thumb_tip = digits[0].distal.next_joint;
+        let pinch_distance = thumb_tip.distance(digits[1].distal.next_joint);
+        let closest_fingertip_distance = digits[1..]
+            .iter()
+            .map(|digit| thumb_tip.distance(digit.distal.next_joint))
+            .fold(f32::INFINITY, f32::min);
+
+        // angles larger than a half turn are clamped, so curling fingers always increases the grab angle
+        let grab_angle = pose.digits[1..]
+            .iter()
+            .map(|angles| angles.flexion.iter().sum::<f32>().clamp(0., PI))
+            .sum::<f32>()
+            / 4.;
+
+        let normal = to_direction(Vec3::NEG_Y);
+        let direction = to_direction(Vec3::NEG_Z);
+        let palm_position = to_point(Vec3::ZERO);
+        let palm_orientation = Quat::from_mat3(&Mat3::from_cols(normal.cross(direction), -normal, -direction));
+        let arm_rotation = bone_rotation(Vec3::NEG_Z, Vec3::Y);
+
+        MyHand {
+            type_,
+            confidence: 1.,
+            visible_time: 0,
+            pinch_distance,
+            grab_angle,
+            pinch_strength: (1.
+                - (closest_fingertip_distance - PINCH_TOUCH_DISTANCE)
+                    / (PINCH_RELEASE_DISTANCE - PINCH_TOUCH_DISTANCE))
+                .clamp(0., 1.),
+            grab_strength: grab_angle / PI,
+            palm: MyPalm {
+                position: palm_position,
+                stabilized_position: palm_position,
+                velocity: Vec3::ZERO,
+                normal,
+                width: self.palm_width,
+                orientation: Vec4::from(palm_orientation),
+            },
+            digits,
+            arm: to_bone(
+                self.wrist + Vec3::Z * self.arm_length,
+                self.wrist,
+                arm_rotation,
+                self.arm_width,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(type_: MyHandType, pose: &HandPose) -> MyHand {
+        let transform = Transform::from_xyz(20., 200., -50.).with_rotation(Quat::from_rotation_y(0.4));
+        HandSkeleton::default().solve(type_, pose, &transform)
+    }
+
+    #[test]
+    fn open_hand_does_not_pinch() {
+        let hand = solve(MyHandType::Right, &HandPose::open());
+
+        assert!(hand.pinch_strength < 0.01, "pinch strength {}", hand.pinch_strength);
+        assert!(hand.grab_strength < 0.01, "grab strength {}", hand.grab_strength);
+    }
+
+    #[test]
+    fn pinch_touches_index_fingertip() {
+        for type_ in [MyHandType::Left, MyHandType::Right] {
+            let hand = solve(type_, &HandPose::pinch());
+
+            assert!(hand.pinch_distance < 10., "pinch distance {}", hand.pinch_distance);
+            assert_eq!(hand.pinch_strength, 1.);
+        }
+    }
+
+    #[test]
+    fn bone_lengths_are_kept() {
+        let skeleton = HandSkeleton::default();
+
+        for type_ in [MyHandType::Left, MyHandType::Right] {
+            for pose in [HandPose::open(), HandPose::pinch(), HandPose::fist()] {
+                let hand = solve(type_, &pose);
+
+                for (digit, digit_skeleton) in hand.digits.iter().zip(skeleton.digits.iter()) {
+                    for (bone, length) in digit.bones().iter().zip(digit_skeleton.lengths) {
+                        assert!((bone.length() - length).abs() < 1e-3, "{} != {}", bone.length(), length);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn curled_digits_are_not_extended() {
+        let open = solve(MyHandType::Right, &HandPose::open());
+        assert!(open.digits.iter().all(|digit| digit.is_extended));
+
+        let fist = solve(MyHandType::Right, &HandPose::fist());
+        assert!(fist.digits[1..].iter().all(|digit| !digit.is_extended));
+
+        let pointing = solve(MyHandType::Left, &HandPose::curled([1., 0., 1., 1., 1.]));
+        let extended = pointing.digits.map(|digit| digit.is_extended);
+        assert_eq!(extended[1..], [true, false, false, false]);
+    }
+}
@@ -0,0 +1,116 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::constant::LEAP_DIGITS_TYPES_ORDER;
+use crate::leap_controller_plugin::{
+    BoneEntities, BoneType, DigitType, HandsData, HandsOrigin, HeldHands, LeapSettings, MyBone, MyDigit, MyHand,
+    MyHandType,
+};
+
+/// Lookups of hands, digits and bones of the latest tracking frame.
+/// Positions are given in the world space, i.e. they include the transform of [`HandsOrigin`].
+#[derive(SystemParam)]
+pub struct HandQuery<'w, 's> {
+    hands_data: Res<'w, HandsData>,
+    bone_entities: Res<'w, BoneEntities>,
+    held_hands: Res<'w, HeldHands>,
+    settings: Res<'w, LeapSettings>,
+    hands_origin_query: Query<'w, 's, &'static GlobalTransform, With<HandsOrigin>>,
+}
+
+fn digit_index(digit_type: DigitType) -> Option<usize> {
+    LEAP_DIGITS_TYPES_ORDER.iter().position(|other| *other == digit_type)
+}
+
+fn digit_bone(digit: &MyDigit, bone_type: BoneType) -> Option<MyBone> {
+    match bone_type {
+        BoneType::Unknown => None,
+        BoneType::Metacarpal => Some(digit.metacarpal),
+        BoneType::Proximal => Some(digit.proximal),
+        BoneType::Intermediate => Some(digit.intermediate),
+        BoneType::Distal => Some(digit.distal),
+    }
+}
+
+impl<'w, 's> HandQuery<'w, 's> {
+    /// Transform of the tracking device space.
+    pub fn origin(&self) -> GlobalTransform {
+        self.hands_origin_query.get_single().copied().unwrap_or_default()
+    }
+
+    /// Point given in the tracking device space, transformed to the world space.
+    pub fn world_point(&self, point: Vec3) -> Vec3 {
+        self.origin().transform_point(point)
+    }
+
+    /// Types of the tracked hands.
+    pub fn hands(&self) -> impl Iterator<Item = MyHandType> + '_ {
+        self.hands_data.hands.iter().map(|hand| hand.type_)
+    }
+
+    /// Whether the hand is lost and only kept with its last pose, see [`HeldHands`].
+    pub fn is_held(&self, hand_type: MyHandType) -> bool {
+        self.held_hands.is_held(hand_type)
+    }
+
+    /// Whether the hand may start interactions: it's tracked, not held, and passes
+    /// [`LeapSettings::min_interaction_confidence`] and [`LeapSettings::min_interaction_time`].
+    pub fn can_interact(&self, hand_type: MyHandType) -> bool {
+        match self.hand(hand_type) {
+            None => false,
+            Some(hand) => {
+                !self.is_held(hand_type)
+                    && hand.confidence >= self.settings.min_interaction_confidence
+                    && hand.visible_seconds() >= self.settings.min_interaction_time
+            }
+        }
+    }
+
+    /// Hand in the world space, like in [`crate::leap_controller_plugin::WorldHandsData`], but of the current frame
+    /// and available also when that resource isn't updated.
+    pub fn world_hand(&self, hand_type: MyHandType) -> Option<MyHand> {
+        Some(self.hand(hand_type)?.transformed(&self.origin()))
+    }
+
+    /// All tracked hands in the world space.
+    pub fn world_hands(&self) -> impl Iterator<Item = MyHand> + '_ {
+        let origin = self.origin();
+        self.hands_data.hands.iter().map(move |hand| hand.transformed(&origin))
+    }
+
+    /// Hand in the tracking device space.
+    pub fn hand(&self, hand_type: MyHandType) -> Option<&MyHand> {
+        self.hands_data.hands.iter().find(|hand| hand.type_ == hand_type)
+    }
+
+    pub fn palm(&self, hand_type: MyHandType) -> Option<Vec3> {
+        self.hand(hand_type).map(|hand| self.world_point(hand.palm.position))
+    }
+
+    pub fn fingertip(&self, hand_type: MyHandType, digit_type: DigitType) -> Option<Vec3> {
+        let digit = &self.hand(hand_type)?.digits[digit_index(digit_type)?];
+        Some(self.world_point(digit.tip()))
+    }
+
+    /// Fingertips of all tracked hands.
+    pub fn fingertips(&self) -> impl Iterator<Item = (MyHandType, DigitType, Vec3)> + '_ {
+        let origin = self.origin();
+        self.hands_data.hands.iter().flat_map(move |hand| {
+            LEAP_DIGITS_TYPES_ORDER
+                .iter()
+                .zip(hand.digits.iter())
+                .map(move |(digit_type, digit)| (hand.type_, *digit_type, origin.transform_point(digit.tip())))
+        })
+    }
+
+    /// Bone with joints and rotation in the world space.
+    pub fn bone(&self, hand_type: MyHandType, digit_type: DigitType, bone_type: BoneType) -> Option<MyBone> {
+        let digit = &self.hand(hand_type)?.digits[digit_index(digit_type)?];
+        Some(digit_bone(digit, bone_type)?.transformed(&self.origin()))
+    }
+
+    /// Entity visualizing the bone, with a [`crate::leap_controller_plugin::BoneComponent`].
+    pub fn bone_entity(&self, hand_type: MyHandType, digit_type: DigitType, bone_type: BoneType) -> Option<Entity> {
+        self.bone_entities.get(hand_type, digit_type, bone_type)
+    }
+}
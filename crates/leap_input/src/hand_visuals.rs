@@ -0,0 +1,365 @@
+use bevy::prelude::shape::{Capsule, Icosphere};
+use bevy::prelude::*;
+
+use crate::leap_controller_plugin::{
+    set_material_opacity, update_bones_transforms, update_hands_opacity, CapsuleVisibility, HandOpacity, HandsData,
+    HandsOrigin, MyHand, MyHandType,
+};
+use crate::rigged_hand::RiggedHand;
+
+const SWITCH_STYLE_KEY: KeyCode = KeyCode::V;
+
+const LINE_THICKNESS: f32 = 2.;
+const FINGERTIP_RADIUS: f32 = 6.;
+
+/// Thumb, index, middle, ring and pinky colors of [`HandStyle::ColoredDigits`].
+const DIGIT_COLORS: [Color; 5] = [Color::RED, Color::ORANGE, Color::YELLOW, Color::GREEN, Color::BLUE];
+
+/// Translucent color of [`HandStyle::Ghost`].
+const GHOST_COLOR: Color = Color::rgba(0.6, 0.8, 1., 0.3);
+
+/// How hands are rendered. `V` switches to the next style.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HandStyle {
+    /// Capsules of [`crate::leap_controller_plugin::BoneComponent`] entities, with palms and arms.
+    #[default]
+    Capsules,
+    /// Small spheres in joints, connected with thin lines.
+    JointsAndBones,
+    /// Translucent, unlit hands.
+    Ghost,
+    /// Each digit in a different color, for debugging.
+    ColoredDigits,
+    /// Only spheres on fingertips.
+    Fingertips,
+    /// Skinned models of [`crate::rigged_hand::RiggedHandPlugin`]; it's skipped when there are no [`RiggedHand`]s.
+    Rigged,
+}
+
+impl HandStyle {
+    pub fn next(self) -> Self {
+        match self {
+            HandStyle::Capsules => HandStyle::JointsAndBones,
+            HandStyle::JointsAndBones => HandStyle::Ghost,
+            HandStyle::Ghost => HandStyle::ColoredDigits,
+            HandStyle::ColoredDigits => HandStyle::Fingertips,
+            HandStyle::Fingertips => HandStyle::Rigged,
+            HandStyle::Rigged => HandStyle::Capsules,
+        }
+    }
+}
+
+/// Renders hands in the selected [`HandStyle`]. Bone capsules are shown only in the [`HandStyle::Capsules`] style;
+/// other styles use their own [`HandVisual`] entities.
+pub struct HandVisualsPlugin;
+
+impl Plugin for HandVisualsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HandStyle>()
+            .add_startup_system(create_hand_visual_assets)
+            .add_system(switch_hand_style)
+            .add_system(
+                apply_hand_style
+                    .after(switch_hand_style)
+                    .before(update_bones_transforms),
+            )
+            .add_system(update_hand_visuals.after(update_bones_transforms))
+            .add_system(update_hand_visuals_opacity.after(update_hands_opacity));
+    }
+}
+
+/// Marks entities drawing hands in styles other than [`HandStyle::Capsules`]. They are children of [`HandsOrigin`].
+#[derive(Component)]
+pub struct HandVisual;
+
+#[derive(Resource)]
+struct HandVisualAssets {
+    /// Sphere with a unit radius.
+    sphere: Handle<Mesh>,
+    /// Capsule with a unit diameter, reaching from `-Y` to `Y`.
+    segment: Handle<Mesh>,
+    left_materials: VisualMaterials,
+    right_materials: VisualMaterials,
+}
+
+impl HandVisualAssets {
+    fn materials(&self, hand_type: MyHandType) -> &VisualMaterials {
+        match hand_type {
+            MyHandType::Left => &self.left_materials,
+            MyHandType::Right => &self.right_materials,
+        }
+    }
+}
+
+/// Materials of a single hand, so hands can fade out separately, see [`HandOpacity`].
+struct VisualMaterials {
+    joint: Handle<StandardMaterial>,
+    line: Handle<StandardMaterial>,
+    ghost: Handle<StandardMaterial>,
+    digits: [Handle<StandardMaterial>; 5],
+}
+
+impl VisualMaterials {
+    /// Materials with alphas and alpha modes of a fully opaque hand.
+    fn with_base_alphas(&self) -> impl Iterator<Item = (&Handle<StandardMaterial>, f32, AlphaMode)> {
+        [
+            (&self.joint, 1., AlphaMode::Opaque),
+            (&self.line, 1., AlphaMode::Opaque),
+        ]
+        .into_iter()
+        .chain(self.digits.iter().map(|digit| (digit, 1., AlphaMode::Opaque)))
+        .chain([(&self.ghost, GHOST_COLOR.a(), AlphaMode::Blend)])
+    }
+}
+
+/// Mesh, material and transform of a single [`HandVisual`].
+struct VisualPart {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+    transform: Transform,
+}
+
+fn create_hand_visual_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mut hand_materials = || {
+        let mut material = |base_color: Color| {
+            materials.add(StandardMaterial {
+                base_color,
+                perceptual_roughness: 0.8,
+                ..default()
+            })
+        };
+
+        VisualMaterials {
+            joint: material(Color::rgb_u8(235, 235, 230)),
+            line: material(Color::rgb_u8(120, 120, 120)),
+            digits: DIGIT_COLORS.map(&mut material),
+            ghost: materials.add(StandardMaterial {
+                base_color: GHOST_COLOR,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            }),
+        }
+    };
+    let left_materials = hand_materials();
+    let right_materials = hand_materials();
+
+    commands.insert_resource(HandVisualAssets {
+        sphere: meshes.add(
+            Icosphere {
+                radius: 1.,
+                subdivisions: 2,
+            }
+            .into(),
+        ),
+        segment: meshes.add(
+            Capsule {
+                radius: 0.5,
+                rings: 0,
+                depth: 1.,
+                ..default()
+            }
+            .into(),
+        ),
+        left_materials,
+        right_materials,
+    });
+}
+
+/// [`HandStyle::Rigged`] is skipped when there are no rigged hands, since nothing would be drawn.
+fn switch_hand_style(
+    keys: Res<Input<KeyCode>>,
+    mut style: ResMut<HandStyle>,
+    rigged_hands_query: Query<(), With<RiggedHand>>,
+) {
+    if keys.just_pressed(SWITCH_STYLE_KEY) {
+        let mut next_style = style.next();
+        if next_style == HandStyle::Rigged && rigged_hands_query.is_empty() {
+            next_style = next_style.next();
+        }
+
+        *style = next_style;
+        info!("Hand style: {:?}", *style);
+    }
+}
+
+/// Bone capsules belong to the capsules style, other styles hide them.
+fn apply_hand_style(style: Res<HandStyle>, mut capsule_visibility: ResMut<CapsuleVisibility>) {
+    let hidden = *style != HandStyle::Capsules;
+    if capsule_visibility.hidden != hidden {
+        capsule_visibility.hidden = hidden;
+    }
+}
+
+/// Joints of a digit from the base of the metacarpal to the tip, with widths of the following bones.
+fn digit_joints(hand: &MyHand, digit_index: usize) -> [(Vec3, f32); 5] {
+    let digit = &hand.digits[digit_index];
+    let bones = digit.bones();
+
+    [
+        (bones[0].prev_joint, bones[0].width),
+        (bones[1].prev_joint, bones[1].width),
+        (bones[2].prev_joint, bones[2].width),
+        (bones[3].prev_joint, bones[3].width),
+        (bones[3].next_joint, bones[3].width),
+    ]
+}
+
+fn sphere(assets: &HandVisualAssets, material: &Handle<StandardMaterial>, center: Vec3, radius: f32) -> VisualPart {
+    VisualPart {
+        mesh: assets.sphere.clone(),
+        material: material.clone(),
+        transform: Transform::from_translation(center).with_scale(Vec3::splat(radius)),
+    }
+}
+
+fn segment(
+    assets: &HandVisualAssets,
+    material: &Handle<StandardMaterial>,
+    start: Vec3,
+    end: Vec3,
+    thickness: f32,
+) -> Option<VisualPart> {
+    let direction = (end - start).try_normalize()?;
+
+    Some(VisualPart {
+        mesh: assets.segment.clone(),
+        material: material.clone(),
+        transform: Transform {
+            translation: (start + end) / 2.,
+            rotation: Quat::from_rotation_arc(Vec3::Y, direction),
+            scale: Vec3::new(thickness, start.distance(end) / 2., thickness),
+        },
+    })
+}
+
+fn hand_visual_parts(style: HandStyle, hand: &MyHand, assets: &HandVisualAssets) -> Vec<VisualPart> {
+    let mut parts = Vec::new();
+    let materials = assets.materials(hand.type_);
+
+    for digit_index in 0..5 {
+        let joints = digit_joints(hand, digit_index);
+
+        match style {
+            HandStyle::Capsules | HandStyle::Rigged => {}
+            HandStyle::Fingertips => {
+                parts.push(sphere(assets, &materials.joint, joints[4].0, FINGERTIP_RADIUS));
+            }
+            HandStyle::JointsAndBones => {
+                for (joint, width) in joints {
+                    parts.push(sphere(assets, &materials.joint, joint, width * 0.3));
+                }
+                for pair in joints.windows(2) {
+                    parts.extend(segment(assets, &materials.line, pair[0].0, pair[1].0, LINE_THICKNESS));
+                }
+            }
+            HandStyle::Ghost | HandStyle::ColoredDigits => {
+                let material = match style {
+                    HandStyle::Ghost => &materials.ghost,
+                    _ => &materials.digits[digit_index],
+                };
+
+                // spheres in joints round off the segments' ends
+                for (joint, width) in joints {
+                    parts.push(sphere(assets, material, joint, width / 2.));
+                }
+                for pair in joints.windows(2) {
+                    parts.extend(segment(assets, material, pair[0].0, pair[1].0, pair[0].1));
+                }
+            }
+        }
+    }
+
+    parts
+}
+
+#[allow(clippy::type_complexity)]
+fn update_hand_visuals(
+    mut commands: Commands,
+    style: Res<HandStyle>,
+    hands_data: Res<HandsData>,
+    assets: Res<HandVisualAssets>,
+    hands_origin_query: Query<Entity, With<HandsOrigin>>,
+    mut visuals_query: Query<
+        (
+            &mut Handle<Mesh>,
+            &mut Handle<StandardMaterial>,
+            &mut Transform,
+            &mut Visibility,
+        ),
+        With<HandVisual>,
+    >,
+) {
+    if !hands_data.is_changed() && !style.is_changed() {
+        return;
+    }
+
+    let mut parts = hands_data
+        .hands
+        .iter()
+        .flat_map(|hand| hand_visual_parts(*style, hand, &assets))
+        .collect::<Vec<_>>();
+
+    let mut visuals_count = 0;
+    for (mut mesh, mut material, mut transform, mut visibility) in visuals_query.iter_mut() {
+        match parts.get(visuals_count) {
+            Some(part) => {
+                *mesh = part.mesh.clone();
+                *material = part.material.clone();
+                *transform = part.transform;
+                visibility.is_visible = true;
+            }
+            None => visibility.is_visible = false,
+        }
+        visuals_count += 1;
+    }
+
+    // the pool grows when there are more parts than entities
+    if parts.len() <= visuals_count {
+        return;
+    }
+    let hands_origin = match hands_origin_query.get_single() {
+        Ok(hands_origin) => hands_origin,
+        Err(_) => return,
+    };
+    commands.entity(hands_origin).with_children(|parent| {
+        for part in parts.drain(visuals_count..) {
+            parent.spawn((
+                PbrBundle {
+                    mesh: part.mesh,
+                    material: part.material,
+                    transform: part.transform,
+                    ..default()
+                },
+                HandVisual,
+            ));
+        }
+    });
+}
+
+fn update_hand_visuals_opacity(
+    opacity: Res<HandOpacity>,
+    assets: Option<Res<HandVisualAssets>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let assets = match assets {
+        Some(assets) if opacity.is_changed() || assets.is_added() => assets,
+        _ => return,
+    };
+
+    for hand_type in [MyHandType::Left, MyHandType::Right] {
+        for (handle, base_alpha, base_alpha_mode) in assets.materials(hand_type).with_base_alphas() {
+            set_material_opacity(
+                &mut materials,
+                handle,
+                opacity.get(hand_type),
+                base_alpha,
+                base_alpha_mode,
+            );
+        }
+    }
+}
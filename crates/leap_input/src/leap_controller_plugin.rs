@@ -1,25 +1,208 @@
 use std::f32::consts::PI;
 
 use bevy::app::{App, Plugin};
-use bevy::prelude::shape::Capsule;
+use bevy::prelude::shape::{Capsule, Icosphere};
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 use leaprs::{Connection, ConnectionConfig, Event};
 
 use crate::constant::LEAP_DIGITS_TYPES_ORDER;
-use crate::leap_controller_plugin::hand::MyHand;
+use crate::leap_controller_plugin::validation::HandValidator;
+
+pub use crate::leap_controller_plugin::bone::MyBone;
+pub use crate::leap_controller_plugin::digit::MyDigit;
+pub use crate::leap_controller_plugin::hand::{MyHand, MyHandType};
+pub use crate::leap_controller_plugin::hand_shape::{HandSize, PalmPlane};
+pub use crate::leap_controller_plugin::palm::MyPalm;
+pub use crate::leap_controller_plugin::validation::{HandAnomaly, HandValidation, TrackingAnomalies};
 
 mod bone;
 mod digit;
 mod hand;
+mod hand_shape;
 mod palm;
+mod validation;
+
+/// The tracking service reports at most two hands, one of each type.
+const MAX_HANDS: usize = 2;
+
+/// Thickness of the flattened sphere showing the palm.
+const PALM_THICKNESS: f32 = 20.;
+
+/// Hands with at least this confidence are opaque, see [`LeapSettings::confidence_opacity`].
+const OPAQUE_CONFIDENCE: f32 = 0.5;
+
+/// Opacity of hands changes in steps, so materials aren't modified on every small change of the confidence.
+const OPACITY_STEP: f32 = 0.05;
+
+/// Settings of [`LeapControllerPlugin`], available as a resource. Visibility of bones can be changed at runtime,
+/// the rest is used at the startup.
+#[derive(Resource, Clone, Debug)]
+pub struct LeapSettings {
+    /// Number of bone entities; each tracked hand uses 20 of them.
+    pub bone_pool_size: usize,
+    pub capsule_radius: f32,
+    /// Length of the cylindrical part of bone capsules.
+    pub capsule_depth: f32,
+    pub bone_color: Color,
+    /// Whether bone capsules, palms and arms are shown. Other visuals can also hide them, see [`CapsuleVisibility`].
+    pub show_bones: bool,
+    /// How long polling of the connection may block a frame, in milliseconds.
+    pub poll_timeout: u32,
+    /// Flags of the `ConnectionConfig`; `None` uses the default config.
+    pub connection_flags: Option<u32>,
+    /// Whether bones get meshes; without it the plugin doesn't need rendering.
+    pub render: bool,
+    /// Whether bone entities with [`BoneComponent`] are spawned at all.
+    pub bone_entities: bool,
+    /// Whether [`WorldHandsData`] is updated.
+    pub world_hands_data: bool,
+    /// How long a lost hand stays in [`HandsData`] with its last pose, in seconds. See [`HeldHands`].
+    pub hold_duration: f64,
+    /// Whether held hands fade out until the end of the hold.
+    pub fade_held_hands: bool,
+    /// Whether hands with a low [`MyHand::confidence`] are translucent; reliable hands stay opaque.
+    pub confidence_opacity: bool,
+    /// How long newly tracked hands fade in, in seconds of [`MyHand::visible_time`].
+    pub fade_in_duration: f64,
+    /// Hands with a lower confidence can't interact, see [`crate::hand_query::HandQuery::can_interact`].
+    pub min_interaction_confidence: f32,
+    /// Hands tracked for a shorter time can't interact, in seconds.
+    pub min_interaction_time: f64,
+    /// Checks of incoming hands; hands failing them are counted in [`TrackingAnomalies`] and left out.
+    /// Rejected hands are held like lost ones; without a hold they keep their last accepted pose instead.
+    pub validation: HandValidation,
+}
+
+impl Default for LeapSettings {
+    fn default() -> Self {
+        LeapSettings {
+            bone_pool_size: 40,
+            capsule_radius: 5.,
+            capsule_depth: 10.,
+            bone_color: Color::rgb_u8(192, 191, 187),
+            show_bones: true,
+            poll_timeout: 25,
+            connection_flags: None,
+            render: true,
+            bone_entities: true,
+            world_hands_data: true,
+            hold_duration: 0.3,
+            fade_held_hands: true,
+            confidence_opacity: true,
+            fade_in_duration: 0.2,
+            min_interaction_confidence: 0.,
+            min_interaction_time: 0.,
+            validation: HandValidation::default(),
+        }
+    }
+}
+
+/// Reads hands from the tracking service into [`HandsData`] and shows them as capsules under [`HandsOrigin`].
+/// Configured with the builder methods, e.g. `LeapControllerPlugin::default().headless()`.
+#[derive(Default)]
+pub struct LeapControllerPlugin {
+    settings: LeapSettings,
+}
+
+impl LeapControllerPlugin {
+    pub fn with_bone_pool_size(mut self, bone_pool_size: usize) -> Self {
+        self.settings.bone_pool_size = bone_pool_size;
+        self
+    }
+
+    pub fn with_capsule(mut self, radius: f32, depth: f32) -> Self {
+        self.settings.capsule_radius = radius;
+        self.settings.capsule_depth = depth;
+        self
+    }
+
+    pub fn with_bone_color(mut self, color: Color) -> Self {
+        self.settings.bone_color = color;
+        self
+    }
+
+    pub fn with_bones_visible(mut self, show_bones: bool) -> Self {
+        self.settings.show_bones = show_bones;
+        self
+    }
+
+    pub fn with_poll_timeout(mut self, milliseconds: u32) -> Self {
+        self.settings.poll_timeout = milliseconds;
+        self
+    }
+
+    pub fn with_connection_flags(mut self, flags: u32) -> Self {
+        self.settings.connection_flags = Some(flags);
+        self
+    }
+
+    /// Bone entities have no meshes, so the app may run without rendering.
+    pub fn headless(mut self) -> Self {
+        self.settings.render = false;
+        self
+    }
+
+    pub fn with_bone_entities(mut self, bone_entities: bool) -> Self {
+        self.settings.bone_entities = bone_entities;
+        self
+    }
+
+    /// Without it [`WorldHandsData`] stays empty; [`crate::hand_query::HandQuery::world_hands`] works either way.
+    pub fn with_world_hands_data(mut self, world_hands_data: bool) -> Self {
+        self.settings.world_hands_data = world_hands_data;
+        self
+    }
+
+    /// Lost hands are kept for `seconds`, so short dropouts don't end grabs; zero drops them immediately.
+    pub fn with_hold(mut self, seconds: f64, fade: bool) -> Self {
+        self.settings.hold_duration = seconds;
+        self.settings.fade_held_hands = fade;
+        self
+    }
 
-pub struct LeapControllerPlugin;
+    pub fn with_confidence_opacity(mut self, confidence_opacity: bool) -> Self {
+        self.settings.confidence_opacity = confidence_opacity;
+        self
+    }
+
+    pub fn with_fade_in(mut self, seconds: f64) -> Self {
+        self.settings.fade_in_duration = seconds;
+        self
+    }
+
+    /// Hands appearing at the edge of the field of view are often misdetected; these thresholds keep them
+    /// from starting grabs and other gestures. Both are zero by default.
+    pub fn with_interaction_thresholds(mut self, min_confidence: f32, min_seconds: f64) -> Self {
+        self.settings.min_interaction_confidence = min_confidence;
+        self.settings.min_interaction_time = min_seconds;
+        self
+    }
+
+    pub fn with_validation(mut self, validation: HandValidation) -> Self {
+        self.settings.validation = validation;
+        self
+    }
+}
 
 impl Plugin for LeapControllerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(create_connection)
+        app.insert_resource(self.settings.clone())
+            .init_resource::<HandsData>()
+            .init_resource::<TrackingFrame>()
+            .init_resource::<TrackingAnomalies>()
+            .init_resource::<BoneEntities>()
+            .init_resource::<HeldHands>()
+            .init_resource::<HandOpacity>()
+            .init_resource::<CapsuleVisibility>()
+            .init_resource::<WorldHandsData>()
+            .add_startup_system(create_connection)
             .add_startup_system(spawn_hands_entities)
-            .add_system(update_hand_data);
+            .add_system(update_hand_data)
+            .add_system(update_bones_transforms.after(update_hand_data))
+            .add_system(update_palms_and_arms_transforms.after(update_bones_transforms))
+            .add_system(update_world_hands_data.after(update_bones_transforms))
+            .add_system(update_hands_opacity.after(update_hand_data));
     }
 }
 
@@ -30,11 +213,24 @@ pub struct HandsOrigin;
 
 #[derive(Component)]
 pub struct BoneComponent {
+    pub hand_type: MyHandType,
     pub digit_type: DigitType,
     pub bone_type: BoneType,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Marks the flattened sphere showing the palm of a hand.
+#[derive(Component)]
+pub struct PalmComponent {
+    pub hand_type: MyHandType,
+}
+
+/// Marks the capsule showing the forearm of a hand.
+#[derive(Component)]
+pub struct ArmComponent {
+    pub hand_type: MyHandType,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum DigitType {
     Unknown,
     Thumb,
@@ -44,7 +240,7 @@ pub enum DigitType {
     Pinky,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum BoneType {
     Unknown,
     Metacarpal,
@@ -53,99 +249,556 @@ pub enum BoneType {
     Distal,
 }
 
-#[derive(Resource)]
+/// Hands from the latest tracking frame, in the tracking device space.
+/// Lost hands stay here for a while with their last pose, [`HeldHands`] tells them apart from tracked ones.
+#[derive(Resource, Default)]
 pub struct HandsData {
     pub hands: Vec<MyHand>,
 }
 
+/// Hands from [`HandsData`] in the world space, i.e. moved by the [`GlobalTransform`] of [`HandsOrigin`].
+/// It is updated every frame, so it follows the origin even when no tracking frame arrives.
+/// Like [`HandsData`], it also contains held hands, see [`HeldHands`].
+#[derive(Resource, Default)]
+pub struct WorldHandsData {
+    pub hands: Vec<MyHand>,
+}
+
+/// Lets plugins drawing hands in their own way hide bone capsules, palms and arms,
+/// without changing [`LeapSettings::show_bones`].
+#[derive(Resource, Default)]
+pub struct CapsuleVisibility {
+    pub hidden: bool,
+}
+
+/// Hands which are no longer tracked, but still are in [`HandsData`] with their last pose,
+/// until [`LeapSettings::hold_duration`] passes. Held hands shouldn't start new interactions.
+#[derive(Resource, Default)]
+pub struct HeldHands {
+    /// Seconds since the startup of the app when the hand was lost.
+    lost_at: HashMap<MyHandType, f64>,
+}
+
+impl HeldHands {
+    pub fn is_held(&self, hand_type: MyHandType) -> bool {
+        self.lost_at.contains_key(&hand_type)
+    }
+
+    pub fn hand_types(&self) -> impl Iterator<Item = MyHandType> + '_ {
+        self.lost_at.keys().copied()
+    }
+
+    /// Part of the hold which is left, from 1 when the hand is lost to 0 when it's dropped; 1 for tracked hands.
+    pub fn remaining(&self, hand_type: MyHandType, now: f64, hold_duration: f64) -> f32 {
+        match self.lost_at.get(&hand_type) {
+            None => 1.,
+            Some(lost_at) if hold_duration > 0. => (1. - (now - lost_at) / hold_duration).clamp(0., 1.) as f32,
+            Some(_) => 0.,
+        }
+    }
+}
+
+/// Opacity of each hand, from the confidence, the fade in of new hands and the remaining part of the hold.
+/// Materials of bones, palms and arms follow it, as well as other hand visuals.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct HandOpacity {
+    left: f32,
+    right: f32,
+}
+
+impl Default for HandOpacity {
+    fn default() -> Self {
+        HandOpacity { left: 1., right: 1. }
+    }
+}
+
+impl HandOpacity {
+    pub fn get(&self, hand_type: MyHandType) -> f32 {
+        match hand_type {
+            MyHandType::Left => self.left,
+            MyHandType::Right => self.right,
+        }
+    }
+
+    fn get_mut(&mut self, hand_type: MyHandType) -> &mut f32 {
+        match hand_type {
+            MyHandType::Left => &mut self.left,
+            MyHandType::Right => &mut self.right,
+        }
+    }
+}
+
+/// Materials of bones, palms and arms, one per hand type, so hands can fade out separately.
+#[derive(Resource)]
+pub struct HandMaterials {
+    left: Handle<StandardMaterial>,
+    right: Handle<StandardMaterial>,
+}
+
+impl HandMaterials {
+    pub fn get(&self, hand_type: MyHandType) -> &Handle<StandardMaterial> {
+        match hand_type {
+            MyHandType::Left => &self.left,
+            MyHandType::Right => &self.right,
+        }
+    }
+}
+
+/// Entities of visible bones, updated together with their transforms.
+#[derive(Resource, Default)]
+pub struct BoneEntities {
+    entities: HashMap<(MyHandType, DigitType, BoneType), Entity>,
+}
+
+impl BoneEntities {
+    pub fn get(&self, hand_type: MyHandType, digit_type: DigitType, bone_type: BoneType) -> Option<Entity> {
+        self.entities.get(&(hand_type, digit_type, bone_type)).copied()
+    }
+}
+
+/// Metadata of the latest tracking frame.
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct TrackingFrame {
+    pub id: i64,
+    /// Time of the tracking service when the frame was captured, in microseconds.
+    pub timestamp: i64,
+    /// Frame rate of the tracking device.
+    pub framerate: f32,
+    /// Seconds since the startup of the app when the frame was received; `None` before the first frame.
+    pub received_at: Option<f64>,
+    /// Frames missing between received ones, counted from gaps in frame ids.
+    pub dropped_frames: u64,
+}
+
 fn create_connection(world: &mut World) {
-    let mut connection = Connection::create(ConnectionConfig::default()).expect("Failed to create connection");
+    let config = match world.resource::<LeapSettings>().connection_flags {
+        None => ConnectionConfig::default(),
+        Some(flags) => ConnectionConfig::new(flags),
+    };
+    let mut connection = Connection::create(config).expect("Failed to create connection");
     connection.open().expect("Failed to open the connection");
 
     world.insert_non_send_resource(connection);
 }
 
+/// Meshes and the material of hand entities; missing in headless apps.
+struct HandMeshes {
+    bone: Handle<Mesh>,
+    palm: Handle<Mesh>,
+    arm: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+/// Rendering assets are optional, so headless apps can spawn bones too.
 fn spawn_hands_entities(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    settings: Res<LeapSettings>,
+    meshes: Option<ResMut<Assets<Mesh>>>,
+    materials: Option<ResMut<Assets<StandardMaterial>>>,
 ) {
-    let capsule = Capsule {
-        radius: 5.,
-        rings: 0,
-        depth: 10.0,
-        ..default()
+    let hand_meshes = match (meshes, materials) {
+        (Some(mut meshes), Some(mut materials)) if settings.render => {
+            let capsule = Capsule {
+                radius: settings.capsule_radius,
+                rings: 0,
+                depth: settings.capsule_depth,
+                ..default()
+            };
+
+            // palms and arms are scaled to the size of the hand
+            let palm = Icosphere {
+                radius: 1.,
+                subdivisions: 2,
+            };
+
+            let arm = Capsule {
+                radius: 0.5,
+                rings: 0,
+                depth: 1.,
+                ..default()
+            };
+
+            let mut debug_material = || {
+                materials.add(StandardMaterial {
+                    base_color: settings.bone_color,
+                    metallic: 0.3,
+                    perceptual_roughness: 0.8,
+                    reflectance: 0.2,
+                    ..default()
+                })
+            };
+            let hand_materials = HandMaterials {
+                left: debug_material(),
+                right: debug_material(),
+            };
+
+            let hand_meshes = HandMeshes {
+                bone: meshes.add(capsule.into()),
+                palm: meshes.add(palm.into()),
+                arm: meshes.add(arm.into()),
+                material: hand_materials.left.clone(),
+            };
+            commands.insert_resource(hand_materials);
+
+            Some(hand_meshes)
+        }
+        _ => None,
     };
 
-    let debug_material = materials.add(StandardMaterial {
-        base_color: Color::rgb_u8(192, 191, 187),
-        metallic: 0.3,
-        perceptual_roughness: 0.8,
-        reflectance: 0.2,
+    let (bones_count, hands_count) = if settings.bone_entities {
+        (settings.bone_pool_size, MAX_HANDS)
+    } else {
+        (0, 0)
+    };
+
+    let invisible = || SpatialBundle {
+        visibility: Visibility::INVISIBLE,
         ..default()
-    });
+    };
 
     commands
         .spawn((SpatialBundle::default(), HandsOrigin))
         .with_children(|parent| {
-            for _ in 0..40 {
-                parent
-                    .spawn(BoneComponent {
+            for _ in 0..bones_count {
+                let mut bone = parent.spawn((
+                    BoneComponent {
+                        hand_type: MyHandType::default(),
                         digit_type: DigitType::Unknown,
                         bone_type: BoneType::Unknown,
-                    })
-                    .insert(PbrBundle {
-                        mesh: meshes.add(capsule.into()),
-                        visibility: Visibility::INVISIBLE,
-                        material: debug_material.clone(),
-                        ..default()
-                    });
+                    },
+                    invisible(),
+                ));
+                if let Some(hand_meshes) = &hand_meshes {
+                    bone.insert((hand_meshes.bone.clone(), hand_meshes.material.clone()));
+                }
+            }
+
+            for _ in 0..hands_count {
+                let mut palm = parent.spawn((
+                    PalmComponent {
+                        hand_type: MyHandType::default(),
+                    },
+                    invisible(),
+                ));
+                if let Some(hand_meshes) = &hand_meshes {
+                    palm.insert((hand_meshes.palm.clone(), hand_meshes.material.clone()));
+                }
+
+                let mut arm = parent.spawn((
+                    ArmComponent {
+                        hand_type: MyHandType::default(),
+                    },
+                    invisible(),
+                ));
+                if let Some(hand_meshes) = &hand_meshes {
+                    arm.insert((hand_meshes.arm.clone(), hand_meshes.material.clone()));
+                }
             }
         });
 }
 
-fn update_hand_data(
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_hand_data(
+    time: Res<Time>,
+    settings: Res<LeapSettings>,
     mut leap_conn: NonSendMut<Connection>,
-    mut digits_query: Query<(&mut Transform, &mut Visibility, &mut BoneComponent)>,
+    mut hands_data: ResMut<HandsData>,
+    mut tracking_frame: ResMut<TrackingFrame>,
+    mut held_hands: ResMut<HeldHands>,
+    mut anomalies: ResMut<TrackingAnomalies>,
+    mut validator: Local<HandValidator>,
 ) {
-    if let Ok(message) = leap_conn.poll(25) {
+    let now = time.elapsed_seconds_f64();
+
+    if let Ok(message) = leap_conn.poll(settings.poll_timeout) {
         match &message.event() {
             Event::Connection(_) => println!("connection event"),
             Event::Device(_) => println!("device event"),
             Event::Tracking(e) => {
-                let mut query_iter = digits_query.iter_mut();
-
-                for hand in e.hands().iter() {
-                    let digits = hand.digits();
-
-                    for (bone_type_index, digit) in digits.iter().enumerate() {
-                        let bones = [
-                            (digit.distal(), BoneType::Distal),
-                            (digit.proximal(), BoneType::Proximal),
-                            (digit.intermediate(), BoneType::Intermediate),
-                            (digit.metacarpal(), BoneType::Metacarpal),
-                        ];
-
-                        for (bone, bone_type) in bones {
-                            let (mut transform, mut visibility, mut bone_component) = query_iter.next().unwrap();
-
-                            bone_component.digit_type = LEAP_DIGITS_TYPES_ORDER[bone_type_index];
-                            bone_component.bone_type = bone_type;
-                            *transform = Transform {
-                                translation: Vec3::from_array(bone.prev_joint().array()),
-                                rotation: Quat::from_array(bone.rotation().array()) * Quat::from_rotation_x(PI / 2.),
-                                ..default()
-                            };
-                            visibility.is_visible = true;
-                        }
+                let id = e.tracking_frame_id();
+                if tracking_frame.received_at.is_some() && id > tracking_frame.id + 1 {
+                    tracking_frame.dropped_frames += (id - tracking_frame.id - 1) as u64;
+                }
+                tracking_frame.id = id;
+                tracking_frame.timestamp = e.info().timestamp();
+                tracking_frame.framerate = e.framerate();
+                tracking_frame.received_at = Some(now);
+
+                let mut hands = e.hands().into_iter().map(MyHand::from).collect::<Vec<_>>();
+                if settings.validation.enabled {
+                    // rejected hands are missing in the frame, so they are held with their last valid pose
+                    hands = validator.validate(hands, tracking_frame.timestamp, &settings.validation, &mut anomalies);
+
+                    // without a hold they would be dropped at once, so they keep the pose until the next frame
+                    if settings.hold_duration <= 0. {
+                        let rejected = hands_data
+                            .hands
+                            .iter()
+                            .filter(|previous| anomalies.latest.contains_key(&previous.type_))
+                            .copied()
+                            .collect::<Vec<_>>();
+                        hands.extend(rejected);
                     }
                 }
+                held_hands
+                    .lost_at
+                    .retain(|hand_type, _| !hands.iter().any(|hand| hand.type_ == *hand_type));
 
-                while let Some((_, mut visibility, _)) = query_iter.next() {
-                    visibility.is_visible = false;
+                // hands missing in the frame keep their last pose for a while
+                if settings.hold_duration > 0. {
+                    for previous in hands_data.hands.iter() {
+                        if !hands.iter().any(|hand| hand.type_ == previous.type_) {
+                            held_hands.lost_at.entry(previous.type_).or_insert(now);
+                            hands.push(*previous);
+                        }
+                    }
                 }
+
+                hands_data.hands = hands;
             }
             _ => {}
         }
     }
+
+    let expired = held_hands
+        .lost_at
+        .iter()
+        .filter(|(_, lost_at)| now - **lost_at >= settings.hold_duration)
+        .map(|(hand_type, _)| *hand_type)
+        .collect::<Vec<_>>();
+    if !expired.is_empty() {
+        held_hands.lost_at.retain(|hand_type, _| !expired.contains(hand_type));
+        hands_data.hands.retain(|hand| !expired.contains(&hand.type_));
+    }
+}
+
+/// Updates [`HandOpacity`] from the confidence, the fade in of new hands and the remaining part of the hold,
+/// see [`HeldHands::remaining`], and sets it to hand materials.
+pub(crate) fn update_hands_opacity(
+    time: Res<Time>,
+    settings: Res<LeapSettings>,
+    hands_data: Res<HandsData>,
+    held_hands: Res<HeldHands>,
+    mut opacity: ResMut<HandOpacity>,
+    hand_materials: Option<Res<HandMaterials>>,
+    mut materials: Option<ResMut<Assets<StandardMaterial>>>,
+) {
+    for hand_type in [MyHandType::Left, MyHandType::Right] {
+        let mut alpha = 1.;
+        if settings.fade_held_hands {
+            alpha *= held_hands.remaining(hand_type, time.elapsed_seconds_f64(), settings.hold_duration);
+        }
+        if let Some(hand) = hands_data.hands.iter().find(|hand| hand.type_ == hand_type) {
+            if settings.confidence_opacity {
+                alpha *= (hand.confidence / OPAQUE_CONFIDENCE).clamp(0., 1.);
+            }
+            if settings.fade_in_duration > 0. {
+                alpha *= (hand.visible_seconds() / settings.fade_in_duration).min(1.) as f32;
+            }
+        }
+
+        let alpha = ((alpha / OPACITY_STEP).round() * OPACITY_STEP).min(1.);
+
+        // the resource is changed only with the opacity, so visuals can skip frames without a change
+        if opacity.get(hand_type) != alpha {
+            *opacity.get_mut(hand_type) = alpha;
+        }
+
+        if let (Some(hand_materials), Some(materials)) = (&hand_materials, &mut materials) {
+            set_material_opacity(materials, hand_materials.get(hand_type), alpha, 1., AlphaMode::Opaque);
+        }
+    }
+}
+
+/// Sets the alpha of the material to `base_alpha * opacity`, blending it while it's translucent
+/// and using `base_alpha_mode` otherwise.
+pub fn set_material_opacity(
+    materials: &mut Assets<StandardMaterial>,
+    handle: &Handle<StandardMaterial>,
+    opacity: f32,
+    base_alpha: f32,
+    base_alpha_mode: AlphaMode,
+) {
+    let alpha = base_alpha * opacity;
+    let alpha_mode = if opacity < 1. {
+        AlphaMode::Blend
+    } else {
+        base_alpha_mode
+    };
+
+    // mutable access marks the material as modified, so it's taken only when something changes
+    let needs_update = matches!(
+        materials.get(handle),
+        Some(material) if material.base_color.a() != alpha || material.alpha_mode != alpha_mode
+    );
+    if !needs_update {
+        return;
+    }
+    if let Some(material) = materials.get_mut(handle) {
+        material.base_color.set_a(alpha);
+        material.alpha_mode = alpha_mode;
+    }
+}
+
+/// Held hands fade out separately, so entities take the material of the hand they currently show.
+fn set_hand_material(
+    material: Option<Mut<Handle<StandardMaterial>>>,
+    hand_materials: &Option<Res<HandMaterials>>,
+    hand_type: MyHandType,
+) {
+    if let (Some(mut material), Some(hand_materials)) = (material, hand_materials) {
+        if *material != *hand_materials.get(hand_type) {
+            *material = hand_materials.get(hand_type).clone();
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_palms_and_arms_transforms(
+    settings: Res<LeapSettings>,
+    hands_data: Res<HandsData>,
+    capsule_visibility: Res<CapsuleVisibility>,
+    hand_materials: Option<Res<HandMaterials>>,
+    mut palms_query: Query<
+        (
+            &mut Transform,
+            &mut Visibility,
+            &mut PalmComponent,
+            Option<&mut Handle<StandardMaterial>>,
+        ),
+        Without<ArmComponent>,
+    >,
+    mut arms_query: Query<
+        (
+            &mut Transform,
+            &mut Visibility,
+            &mut ArmComponent,
+            Option<&mut Handle<StandardMaterial>>,
+        ),
+        Without<PalmComponent>,
+    >,
+) {
+    if !hands_data.is_changed() && !settings.is_changed() && !capsule_visibility.is_changed() {
+        return;
+    }
+    let show_bones = settings.show_bones && !capsule_visibility.hidden;
+
+    let mut palms_iter = palms_query.iter_mut();
+    let mut arms_iter = arms_query.iter_mut();
+
+    for hand in hands_data.hands.iter() {
+        if let Some((mut transform, mut visibility, mut palm_component, material)) = palms_iter.next() {
+            palm_component.hand_type = hand.type_;
+            // the palm lies in the `XZ` plane of its orientation
+            *transform = Transform {
+                translation: hand.palm.position,
+                rotation: Quat::from_vec4(hand.palm.orientation),
+                scale: Vec3::new(hand.palm.width / 2., PALM_THICKNESS / 2., hand.palm.width / 2.),
+            };
+            visibility.is_visible = show_bones;
+            set_hand_material(material, &hand_materials, hand.type_);
+        }
+
+        if let Some((mut transform, mut visibility, mut arm_component, material)) = arms_iter.next() {
+            let arm = &hand.arm;
+            arm_component.hand_type = hand.type_;
+            // the arm capsule reaches from `-Y` to `Y` and has a unit diameter
+            *transform = Transform {
+                translation: (arm.prev_joint + arm.next_joint) / 2.,
+                rotation: arm.rotation * Quat::from_rotation_x(PI / 2.),
+                scale: Vec3::new(arm.width, arm.length() / 2., arm.width),
+            };
+            visibility.is_visible = show_bones;
+            set_hand_material(material, &hand_materials, hand.type_);
+        }
+    }
+
+    for (_, mut visibility, _, _) in palms_iter {
+        visibility.is_visible = false;
+    }
+    for (_, mut visibility, _, _) in arms_iter {
+        visibility.is_visible = false;
+    }
+}
+
+pub fn update_world_hands_data(
+    settings: Res<LeapSettings>,
+    hands_data: Res<HandsData>,
+    mut world_hands_data: ResMut<WorldHandsData>,
+    hands_origin_query: Query<&GlobalTransform, With<HandsOrigin>>,
+) {
+    if !settings.world_hands_data {
+        return;
+    }
+
+    // global transforms are propagated at the end of the frame, so a moving origin is a frame behind
+    let hands_origin = hands_origin_query.get_single().copied().unwrap_or_default();
+
+    world_hands_data.hands = hands_data
+        .hands
+        .iter()
+        .map(|hand| hand.transformed(&hands_origin))
+        .collect();
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_bones_transforms(
+    settings: Res<LeapSettings>,
+    hands_data: Res<HandsData>,
+    capsule_visibility: Res<CapsuleVisibility>,
+    hand_materials: Option<Res<HandMaterials>>,
+    mut bone_entities: ResMut<BoneEntities>,
+    mut digits_query: Query<(
+        Entity,
+        &mut Transform,
+        &mut Visibility,
+        &mut BoneComponent,
+        Option<&mut Handle<StandardMaterial>>,
+    )>,
+) {
+    if !hands_data.is_changed() && !settings.is_changed() && !capsule_visibility.is_changed() {
+        return;
+    }
+    let show_bones = settings.show_bones && !capsule_visibility.hidden;
+
+    bone_entities.entities.clear();
+
+    let mut query_iter = digits_query.iter_mut();
+
+    for hand in hands_data.hands.iter() {
+        for (bone_type_index, digit) in hand.digits.iter().enumerate() {
+            let bones = [
+                (digit.distal, BoneType::Distal),
+                (digit.proximal, BoneType::Proximal),
+                (digit.intermediate, BoneType::Intermediate),
+                (digit.metacarpal, BoneType::Metacarpal),
+            ];
+
+            for (bone, bone_type) in bones {
+                let (entity, mut transform, mut visibility, mut bone_component, material) = match query_iter.next() {
+                    // the pool is too small for all tracked bones
+                    None => return,
+                    Some(bone_entity) => bone_entity,
+                };
+
+                bone_component.hand_type = hand.type_;
+                bone_component.digit_type = LEAP_DIGITS_TYPES_ORDER[bone_type_index];
+                bone_component.bone_type = bone_type;
+                *transform = Transform {
+                    translation: bone.prev_joint,
+                    rotation: bone.rotation * Quat::from_rotation_x(PI / 2.),
+                    ..default()
+                };
+                visibility.is_visible = show_bones;
+                set_hand_material(material, &hand_materials, hand.type_);
+                bone_entities
+                    .entities
+                    .insert((hand.type_, bone_component.digit_type, bone_type), entity);
+            }
+        }
+    }
+
+    for (_, _, mut visibility, _, _) in query_iter {
+        visibility.is_visible = false;
+    }
 }
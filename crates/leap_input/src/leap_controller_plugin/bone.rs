@@ -1,5 +1,5 @@
 use bevy::math::Vec3;
-use bevy::prelude::Quat;
+use bevy::prelude::{GlobalTransform, Quat};
 use leaprs::Bone;
 
 #[derive(Copy, Clone, Debug, Default)]
@@ -28,3 +28,39 @@ impl From<Bone<'_>> for MyBone {
         }
     }
 }
+
+impl MyBone {
+    pub fn length(&self) -> f32 {
+        self.prev_joint.distance(self.next_joint)
+    }
+
+    /// Unit vector from the previous to the next joint.
+    /// Bones with zero length (e.g. thumb's metacarpal) use the direction of their rotation.
+    pub fn direction(&self) -> Vec3 {
+        match (self.next_joint - self.prev_joint).try_normalize() {
+            Some(direction) => direction,
+            None => self.rotation * Vec3::NEG_Z,
+        }
+    }
+
+    /// The bone moved from the tracking device space by `transform`, e.g. the one of `HandsOrigin`.
+    /// The width is scaled by the `X` scale of the transform.
+    pub fn transformed(&self, transform: &GlobalTransform) -> Self {
+        let (scale, rotation, _) = transform.to_scale_rotation_translation();
+
+        MyBone {
+            prev_joint: transform.transform_point(self.prev_joint),
+            next_joint: transform.transform_point(self.next_joint),
+            width: self.width * scale.x,
+            rotation: rotation * self.rotation,
+        }
+    }
+
+    /// Unit vector pointing out of the back of the bone (the side of the nail), perpendicular to its direction.
+    pub fn dorsal(&self) -> Vec3 {
+        let direction = self.direction();
+        let up = self.rotation * Vec3::Y;
+
+        (up - direction * up.dot(direction)).normalize_or_zero()
+    }
+}
@@ -1,3 +1,5 @@
+use bevy::math::Vec3;
+use bevy::prelude::GlobalTransform;
 use leaprs::Digit;
 
 use crate::leap_controller_plugin::bone::MyBone;
@@ -35,3 +37,29 @@ impl From<&Digit<'_>> for MyDigit {
         }
     }
 }
+
+impl MyDigit {
+    /// Bones from the metacarpal to the distal phalange.
+    pub fn bones(&self) -> [MyBone; 4] {
+        [self.metacarpal, self.proximal, self.intermediate, self.distal]
+    }
+
+    pub fn tip(&self) -> Vec3 {
+        self.distal.next_joint
+    }
+
+    pub fn transformed(&self, transform: &GlobalTransform) -> Self {
+        MyDigit {
+            metacarpal: self.metacarpal.transformed(transform),
+            proximal: self.proximal.transformed(transform),
+            intermediate: self.intermediate.transformed(transform),
+            distal: self.distal.transformed(transform),
+            is_extended: self.is_extended,
+        }
+    }
+
+    /// Sum of the bones' lengths, including the metacarpal.
+    pub fn length(&self) -> f32 {
+        self.bones().iter().map(MyBone::length).sum()
+    }
+}
@@ -1,10 +1,10 @@
-use bevy::prelude::Component;
+use bevy::prelude::{Component, GlobalTransform};
 use leaprs::{Hand, HandType};
 use crate::leap_controller_plugin::bone::MyBone;
 use crate::leap_controller_plugin::digit::MyDigit;
 use crate::leap_controller_plugin::palm::MyPalm;
 
-#[derive(Debug, Clone, Component, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Component, Copy, PartialEq, Eq, Hash)]
 pub enum MyHandType {
     Left,
     Right,
@@ -61,6 +61,26 @@ pub struct MyHand {
     pub arm: MyBone,
 }
 
+impl MyHand {
+    pub fn visible_seconds(&self) -> f64 {
+        self.visible_time as f64 / 1_000_000.
+    }
+
+    /// The hand moved from the tracking device space by `transform`. Distances and widths are scaled like positions,
+    /// by the `X` scale of the transform, which is expected to be uniform; angles are kept.
+    pub fn transformed(&self, transform: &GlobalTransform) -> Self {
+        let (scale, _, _) = transform.to_scale_rotation_translation();
+
+        MyHand {
+            pinch_distance: self.pinch_distance * scale.x,
+            palm: self.palm.transformed(transform),
+            digits: self.digits.map(|digit| digit.transformed(transform)),
+            arm: self.arm.transformed(transform),
+            ..*self
+        }
+    }
+}
+
 impl From<Hand<'_>> for MyHand {
     fn from(hand: Hand) -> Self {
         let digits = hand.digits();
@@ -0,0 +1,192 @@
+use bevy::math::{Mat3, Vec3};
+
+use crate::hand_model::{DigitAngles, HandPose};
+use crate::leap_controller_plugin::bone::MyBone;
+use crate::leap_controller_plugin::hand::{MyHand, MyHandType};
+
+/// Plane fitted to the palm. The normal points out of the palm, on the same side as the palm normal.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct PalmPlane {
+    pub point: Vec3,
+    pub normal: Vec3,
+    /// Root mean square distance of the palm joints from the plane; grows when the palm is cupped.
+    pub error: f32,
+}
+
+impl PalmPlane {
+    /// Signed distance from the plane, positive on the side of the normal.
+    pub fn distance(&self, point: Vec3) -> f32 {
+        (point - self.point).dot(self.normal)
+    }
+}
+
+/// Dimensions of a hand, in millimeters.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct HandSize {
+    pub palm_width: f32,
+    /// Length of the middle finger along its bones, i.e. the sum of their lengths, including the metacarpal.
+    pub length: f32,
+    /// Lengths of digits, including metacarpals, in the order of [`MyHand::digits`].
+    pub digit_lengths: [f32; 5],
+}
+
+/// Flexion and side rotation of `bone` relative to `parent`, in radians.
+/// Positive abduction moves the bone toward the negative `X` axis of the parent, like in [`crate::hand_model`].
+fn joint_angles(parent: &MyBone, bone: &MyBone) -> (f32, f32) {
+    let forward = parent.direction();
+    let up = parent.dorsal();
+    let right = up.cross(-forward);
+    let direction = bone.direction();
+
+    let (x, y, z) = (direction.dot(right), direction.dot(up), direction.dot(forward));
+    let flexion = (-y).atan2(Vec3::new(x, 0., z).length());
+    let abduction = (-x).atan2(z);
+
+    (flexion, abduction)
+}
+
+impl MyHand {
+    /// Joint angles of a single digit, following the conventions of [`DigitAngles`].
+    /// It is the inverse of [`crate::hand_model::HandSkeleton::solve`].
+    pub fn digit_angles(&self, index: usize) -> DigitAngles {
+        let bones = self.digits[index].bones();
+
+        let (knuckle_flexion, mut abduction) = joint_angles(&bones[0], &bones[1]);
+        if self.type_ == MyHandType::Left {
+            // left hands are mirrored, the thumb is on the positive `X` side of bones
+            abduction = -abduction;
+        }
+
+        // middle and last joints only bend, so the full angle is kept, even when it exceeds a right angle
+        let hinge_flexion = |parent: &MyBone, bone: &MyBone| {
+            let direction = bone.direction();
+            (-direction.dot(parent.dorsal())).atan2(direction.dot(parent.direction()))
+        };
+
+        DigitAngles {
+            flexion: [
+                knuckle_flexion,
+                hinge_flexion(&bones[1], &bones[2]),
+                hinge_flexion(&bones[2], &bones[3]),
+            ],
+            abduction,
+        }
+    }
+
+    /// Joint angles of all digits.
+    pub fn pose(&self) -> HandPose {
+        HandPose {
+            digits: [0, 1, 2, 3, 4].map(|index| self.digit_angles(index)),
+        }
+    }
+
+    /// Distances of fingertips from the palm center, in the order of [`MyHand::digits`].
+    pub fn fingertip_palm_distances(&self) -> [f32; 5] {
+        self.digits.map(|digit| digit.tip().distance(self.palm.position))
+    }
+
+    /// Angles between neighbouring digits (thumb-index, index-middle, middle-ring and ring-pinky),
+    /// measured between their proximal bones projected onto the palm plane.
+    pub fn finger_spread(&self) -> [f32; 4] {
+        let normal = self.palm.normal;
+        let projected = self
+            .digits
+            .map(|digit| digit.proximal.direction().reject_from_normalized(normal));
+
+        [0, 1, 2, 3].map(|index| projected[index].angle_between(projected[index + 1]))
+    }
+
+    /// Least squares plane through the palm center and both ends of the fingers' metacarpals.
+    /// The thumb is left out, since its base sits below the palm.
+    pub fn palm_plane(&self) -> PalmPlane {
+        let mut points = vec![self.palm.position];
+        for digit in self.digits[1..].iter() {
+            points.push(digit.metacarpal.prev_joint);
+            points.push(digit.metacarpal.next_joint);
+        }
+
+        let centroid = points.iter().sum::<Vec3>() / points.len() as f32;
+        let covariance = points.iter().fold(Mat3::ZERO, |sum, point| {
+            let offset = *point - centroid;
+            sum + Mat3::from_cols(offset * offset.x, offset * offset.y, offset * offset.z)
+        });
+
+        // the normal is the direction of the smallest spread of points; a few power iterations on the inverse
+        // of the covariance find it, starting from the reported palm normal
+        let mut normal = self.palm.normal.normalize_or_zero();
+        if covariance.determinant().abs() > f32::EPSILON {
+            let inverse = covariance.inverse();
+            for _ in 0..8 {
+                normal = (inverse * normal).normalize_or_zero();
+            }
+        }
+        if normal.dot(self.palm.normal) < 0. {
+            normal = -normal;
+        }
+
+        let error = (points
+            .iter()
+            .map(|point| (*point - centroid).dot(normal).powi(2))
+            .sum::<f32>()
+            / points.len() as f32)
+            .sqrt();
+
+        PalmPlane {
+            point: centroid,
+            normal,
+            error,
+        }
+    }
+
+    pub fn size(&self) -> HandSize {
+        let digit_lengths = self.digits.map(|digit| digit.length());
+
+        HandSize {
+            palm_width: self.palm.width,
+            length: digit_lengths[2],
+            digit_lengths,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Quat;
+    use bevy::transform::components::Transform;
+
+    use crate::hand_model::HandSkeleton;
+
+    use super::*;
+
+    fn spread() -> HandPose {
+        let mut pose = HandPose::curled([0.3, 0.6, 0.1, 0.4, 0.8]);
+        for (digit, abduction) in pose.digits.iter_mut().zip([0.3, 0.15, 0.05, -0.1, -0.2]) {
+            digit.abduction = abduction;
+        }
+
+        pose
+    }
+
+    #[test]
+    fn digit_angles_invert_solve() {
+        let transform = Transform::from_xyz(-30., 180., 40.).with_rotation(Quat::from_rotation_x(-0.3));
+
+        for type_ in [MyHandType::Left, MyHandType::Right] {
+            for pose in [HandPose::open(), HandPose::pinch(), HandPose::fist(), spread()] {
+                let hand = HandSkeleton::default().solve(type_, &pose, &transform);
+
+                for (index, angles) in pose.digits.iter().enumerate() {
+                    let solved = hand.digit_angles(index);
+                    let expected = angles.flexion.iter().chain([&angles.abduction]);
+                    let actual = solved.flexion.iter().chain([&solved.abduction]);
+                    for (expected, actual) in expected.zip(actual) {
+                        assert!(
+                            (expected - actual).abs() < 1e-3,
+                            "{type_:?} digit {index}: {angles:?} != {solved:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
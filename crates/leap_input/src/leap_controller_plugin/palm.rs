@@ -1,4 +1,5 @@
-use bevy::math::{Vec3, Vec4};
+use bevy::math::{Quat, Vec3, Vec4};
+use bevy::prelude::GlobalTransform;
 use leaprs::Palm;
 
 #[derive(Copy, Clone, Debug, Default)]
@@ -31,6 +32,22 @@ pub struct MyPalm {
     pub orientation: Vec4,
 }
 
+impl MyPalm {
+    /// The palm moved by `transform`, see [`crate::leap_controller_plugin::MyHand::transformed`].
+    pub fn transformed(&self, transform: &GlobalTransform) -> Self {
+        let (scale, rotation, _) = transform.to_scale_rotation_translation();
+
+        MyPalm {
+            position: transform.transform_point(self.position),
+            stabilized_position: transform.transform_point(self.stabilized_position),
+            velocity: transform.affine().transform_vector3(self.velocity),
+            normal: rotation * self.normal,
+            width: self.width * scale.x,
+            orientation: Vec4::from(rotation * Quat::from_vec4(self.orientation)),
+        }
+    }
+}
+
 impl From<Palm<'_>> for MyPalm {
     fn from(leaprs_palm: Palm) -> Self {
         MyPalm {
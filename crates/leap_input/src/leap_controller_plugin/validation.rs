@@ -0,0 +1,343 @@
+use bevy::math::{Quat, Vec3, Vec4};
+use bevy::prelude::Resource;
+use bevy::utils::HashMap;
+
+use crate::leap_controller_plugin::bone::MyBone;
+use crate::leap_controller_plugin::hand::{MyHand, MyHandType};
+
+/// Thresholds of the checks of incoming hands, see [`crate::leap_controller_plugin::LeapSettings::validation`].
+#[derive(Clone, Debug)]
+pub struct HandValidation {
+    pub enabled: bool,
+    /// Bones longer than this are implausible, in millimeters. The arm isn't checked.
+    pub max_bone_length: f32,
+    /// Palms moving faster than this are considered teleported, in millimeters per second.
+    pub max_palm_speed: f32,
+    /// Maximal speed of joints relative to the palm, in millimeters per second.
+    pub max_joint_speed: f32,
+    /// Hands are compared with the previous accepted one only if it's at most this old, in seconds,
+    /// so a hand which really moved that fast is accepted again after a while.
+    pub max_frame_gap: f64,
+}
+
+impl Default for HandValidation {
+    fn default() -> Self {
+        HandValidation {
+            enabled: true,
+            max_bone_length: 120.,
+            max_palm_speed: 5000.,
+            max_joint_speed: 5000.,
+            max_frame_gap: 0.25,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HandAnomaly {
+    /// Some value is NaN or infinite.
+    NotFinite,
+    /// A bone is longer than [`HandValidation::max_bone_length`].
+    BoneLength,
+    /// Joints moved relative to the palm faster than [`HandValidation::max_joint_speed`].
+    JointJump,
+    /// The palm moved faster than [`HandValidation::max_palm_speed`].
+    PalmTeleport,
+}
+
+/// Counts of anomalies found in incoming hands since the startup.
+/// Hands with anomalies are left out of their frames, so they are held like lost hands.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct TrackingAnomalies {
+    pub not_finite: u64,
+    pub bone_length: u64,
+    pub joint_jump: u64,
+    pub palm_teleport: u64,
+    pub rejected_hands: u64,
+    /// Hands with normalized rotations or clamped strengths.
+    pub repaired_hands: u64,
+    /// Anomalies of hands in the latest tracking frame.
+    pub latest: HashMap<MyHandType, Vec<HandAnomaly>>,
+}
+
+impl TrackingAnomalies {
+    pub fn total(&self) -> u64 {
+        self.not_finite + self.bone_length + self.joint_jump + self.palm_teleport
+    }
+
+    fn add(&mut self, anomaly: HandAnomaly) {
+        match anomaly {
+            HandAnomaly::NotFinite => self.not_finite += 1,
+            HandAnomaly::BoneLength => self.bone_length += 1,
+            HandAnomaly::JointJump => self.joint_jump += 1,
+            HandAnomaly::PalmTeleport => self.palm_teleport += 1,
+        }
+    }
+}
+
+fn bone_is_finite(bone: &MyBone) -> bool {
+    bone.prev_joint.is_finite() && bone.next_joint.is_finite() && bone.width.is_finite() && bone.rotation.is_finite()
+}
+
+/// Normalizes the rotation, unless it can't be recovered; returns whether it changed.
+fn repair_rotation(rotation: &mut Quat) -> bool {
+    if rotation.is_normalized() {
+        return false;
+    }
+
+    *rotation = if rotation.length() > f32::EPSILON {
+        rotation.normalize()
+    } else {
+        Quat::IDENTITY
+    };
+    true
+}
+
+impl MyHand {
+    pub fn is_finite(&self) -> bool {
+        let palm = &self.palm;
+
+        self.confidence.is_finite()
+            && self.pinch_distance.is_finite()
+            && self.grab_angle.is_finite()
+            && self.pinch_strength.is_finite()
+            && self.grab_strength.is_finite()
+            && palm.position.is_finite()
+            && palm.stabilized_position.is_finite()
+            && palm.velocity.is_finite()
+            && palm.normal.is_finite()
+            && palm.width.is_finite()
+            && palm.orientation.is_finite()
+            && bone_is_finite(&self.arm)
+            && self
+                .digits
+                .iter()
+                .flat_map(|digit| digit.bones())
+                .all(|bone| bone_is_finite(&bone))
+    }
+
+    /// Normalizes rotations and clamps normalized values to `0..=1`; returns whether anything changed.
+    /// Values have to be finite.
+    pub fn repair(&mut self) -> bool {
+        let mut repaired = false;
+
+        for value in [&mut self.confidence, &mut self.pinch_strength, &mut self.grab_strength] {
+            repaired |= !(0. ..=1.).contains(value);
+            *value = value.clamp(0., 1.);
+        }
+
+        let mut orientation = Quat::from_vec4(self.palm.orientation);
+        if repair_rotation(&mut orientation) {
+            self.palm.orientation = Vec4::from(orientation);
+            repaired = true;
+        }
+
+        repaired |= repair_rotation(&mut self.arm.rotation);
+        for digit in self.digits.iter_mut() {
+            for bone in [
+                &mut digit.metacarpal,
+                &mut digit.proximal,
+                &mut digit.intermediate,
+                &mut digit.distal,
+            ] {
+                repaired |= repair_rotation(&mut bone.rotation);
+            }
+        }
+
+        repaired
+    }
+
+    /// Joints of all digits relative to the palm position.
+    fn palm_relative_joints(&self) -> impl Iterator<Item = Vec3> + '_ {
+        self.digits
+            .iter()
+            .flat_map(|digit| digit.bones())
+            .flat_map(|bone| [bone.prev_joint, bone.next_joint])
+            .map(|joint| joint - self.palm.position)
+    }
+
+    /// Anomalies of the hand. `previous` is the same hand `seconds` earlier; without it continuity isn't checked.
+    pub fn anomalies(&self, previous: Option<(&MyHand, f32)>, validation: &HandValidation) -> Vec<HandAnomaly> {
+        if !self.is_finite() {
+            return vec![HandAnomaly::NotFinite];
+        }
+
+        let mut anomalies = Vec::new();
+
+        let is_too_long = |bone: MyBone| bone.length() > validation.max_bone_length;
+        if self.digits.iter().flat_map(|digit| digit.bones()).any(is_too_long) {
+            anomalies.push(HandAnomaly::BoneLength);
+        }
+
+        let (previous, seconds) = match previous {
+            Some((previous, seconds)) if seconds > 0. => (previous, seconds),
+            _ => return anomalies,
+        };
+
+        if self.palm.position.distance(previous.palm.position) > validation.max_palm_speed * seconds {
+            anomalies.push(HandAnomaly::PalmTeleport);
+        }
+
+        let max_joint_distance = validation.max_joint_speed * seconds;
+        let joint_jumped = self
+            .palm_relative_joints()
+            .zip(previous.palm_relative_joints())
+            .any(|(joint, previous_joint)| joint.distance(previous_joint) > max_joint_distance);
+        if joint_jumped {
+            anomalies.push(HandAnomaly::JointJump);
+        }
+
+        anomalies
+    }
+}
+
+/// Latest accepted hands, which the next frames are compared with.
+#[derive(Default)]
+pub(crate) struct HandValidator {
+    /// Hands with timestamps of their frames, in microseconds.
+    accepted: HashMap<MyHandType, (MyHand, i64)>,
+}
+
+impl HandValidator {
+    /// Repairs hands of a frame and leaves out the ones with anomalies, counting both in `anomalies`.
+    pub(crate) fn validate(
+        &mut self,
+        hands: Vec<MyHand>,
+        timestamp: i64,
+        validation: &HandValidation,
+        anomalies: &mut TrackingAnomalies,
+    ) -> Vec<MyHand> {
+        anomalies.latest.clear();
+
+        let mut valid_hands = Vec::with_capacity(hands.len());
+        for mut hand in hands {
+            let previous = self
+                .accepted
+                .get(&hand.type_)
+                .and_then(|(previous, previous_timestamp)| {
+                    let seconds = (timestamp - previous_timestamp) as f64 / 1_000_000.;
+                    (seconds <= validation.max_frame_gap).then_some((previous, seconds as f32))
+                });
+
+            let hand_anomalies = hand.anomalies(previous, validation);
+            if !hand_anomalies.is_empty() {
+                for anomaly in hand_anomalies.iter() {
+                    anomalies.add(*anomaly);
+                }
+                anomalies.rejected_hands += 1;
+                anomalies.latest.insert(hand.type_, hand_anomalies);
+                continue;
+            }
+
+            if hand.repair() {
+                anomalies.repaired_hands += 1;
+            }
+            self.accepted.insert(hand.type_, (hand, timestamp));
+            valid_hands.push(hand);
+        }
+
+        valid_hands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::transform::components::Transform;
+
+    use crate::hand_model::{HandPose, HandSkeleton};
+
+    use super::*;
+
+    fn hand_at(x: f32) -> MyHand {
+        let transform = Transform::from_xyz(x, 200., 0.);
+        HandSkeleton::default().solve(MyHandType::Right, &HandPose::open(), &transform)
+    }
+
+    #[test]
+    fn solved_hand_is_valid() {
+        let mut hand = hand_at(0.);
+
+        assert!(hand
+            .anomalies(Some((&hand_at(0.), 0.01)), &HandValidation::default())
+            .is_empty());
+        assert!(!hand.repair());
+    }
+
+    #[test]
+    fn not_finite_values_are_found() {
+        let mut hand = hand_at(0.);
+        hand.digits[2].intermediate.next_joint.y = f32::NAN;
+
+        assert_eq!(
+            hand.anomalies(None, &HandValidation::default()),
+            vec![HandAnomaly::NotFinite]
+        );
+    }
+
+    #[test]
+    fn too_long_bones_are_found() {
+        let mut hand = hand_at(0.);
+        let distal = &mut hand.digits[1].distal;
+        distal.next_joint = distal.prev_joint + distal.direction() * 150.;
+
+        assert_eq!(
+            hand.anomalies(None, &HandValidation::default()),
+            vec![HandAnomaly::BoneLength]
+        );
+    }
+
+    #[test]
+    fn palm_teleport_is_checked_within_frame_gap() {
+        let validation = HandValidation::default();
+        let mut anomalies = TrackingAnomalies::default();
+        let mut validator = HandValidator::default();
+
+        assert_eq!(
+            validator
+                .validate(vec![hand_at(0.)], 0, &validation, &mut anomalies)
+                .len(),
+            1
+        );
+
+        // 100 mm in 10 ms is too fast, joints keep their places relative to the palm
+        let hands = validator.validate(vec![hand_at(100.)], 10_000, &validation, &mut anomalies);
+        assert!(hands.is_empty());
+        assert_eq!(anomalies.latest[&MyHandType::Right], vec![HandAnomaly::PalmTeleport]);
+        assert_eq!((anomalies.palm_teleport, anomalies.rejected_hands), (1, 1));
+
+        // the previous accepted hand is older than the frame gap, so the hand isn't compared with it
+        let hands = validator.validate(vec![hand_at(100.)], 300_000, &validation, &mut anomalies);
+        assert_eq!(hands.len(), 1);
+        assert!(anomalies.latest.is_empty());
+        assert_eq!(anomalies.total(), 1);
+    }
+
+    #[test]
+    fn normalized_values_are_clamped() {
+        let mut hand = hand_at(0.);
+        hand.confidence = 1.5;
+        hand.pinch_strength = -0.2;
+        hand.grab_strength = 0.4;
+
+        assert!(hand.repair());
+        assert_eq!(
+            (hand.confidence, hand.pinch_strength, hand.grab_strength),
+            (1., 0., 0.4)
+        );
+    }
+
+    #[test]
+    fn rotations_are_repaired() {
+        let mut hand = hand_at(0.);
+        hand.arm.rotation = Quat::from_xyzw(0., 0., 0., 0.);
+        hand.palm.orientation = Vec4::ZERO;
+        hand.digits[3].proximal.rotation = Quat::from_xyzw(0., 0., 0., 2.);
+
+        let mut anomalies = TrackingAnomalies::default();
+        let hands = HandValidator::default().validate(vec![hand], 0, &HandValidation::default(), &mut anomalies);
+
+        assert_eq!(anomalies.repaired_hands, 1);
+        assert_eq!(hands[0].arm.rotation, Quat::IDENTITY);
+        assert_eq!(hands[0].palm.orientation, Vec4::from(Quat::IDENTITY));
+        assert_eq!(hands[0].digits[3].proximal.rotation, Quat::IDENTITY);
+    }
+}
@@ -1,2 +1,9 @@
+pub mod hand_history;
+pub mod hand_model;
+pub mod hand_query;
+pub mod hand_visuals;
 pub mod leap_controller_plugin;
+pub mod rigged_hand;
+pub mod synthetic_hand;
+pub mod tracking_diagnostics;
 mod constant;
@@ -0,0 +1,336 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::hand_visuals::HandStyle;
+use crate::leap_controller_plugin::{
+    set_material_opacity, update_bones_transforms, update_hands_opacity, HandOpacity, HandsData, HandsOrigin,
+    MyHandType,
+};
+
+/// Drives skinned hand models, e.g. loaded from glTF, with tracked hands.
+/// Spawn the model's scene with a [`RiggedHand`]; joints are found by their names once the scene is loaded.
+/// Each hand gets its own copies of the model's materials, which follow its [`HandOpacity`].
+pub struct RiggedHandPlugin;
+
+impl Plugin for RiggedHandPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(bind_rigged_hands)
+            .add_system(
+                update_rigged_hands
+                    .after(bind_rigged_hands)
+                    .after(update_bones_transforms),
+            )
+            .add_system(
+                update_rigged_hands_opacity
+                    .after(bind_rigged_hands)
+                    .after(update_hands_opacity),
+            );
+    }
+}
+
+/// Names of the model's joints. Digit joints are given in the order of [`crate::leap_controller_plugin::MyHand::digits`]
+/// and bones (metacarpal, proximal, intermediate, distal); bones without a joint are skipped.
+#[derive(Clone, Debug)]
+pub struct HandJointNames {
+    pub wrist: String,
+    pub digits: [[Option<String>; 4]; 5],
+}
+
+impl HandJointNames {
+    /// Names like `L_Wrist`, `L_index_meta`, `L_index_a`, `L_index_b` and `L_index_c`, used by many hand rigs.
+    /// Thumbs of such rigs have no joint for the zero-length metacarpal of the tracking data.
+    pub fn with_prefix(prefix: &str) -> Self {
+        let digit = |name: &str| ["meta", "a", "b", "c"].map(|bone| Some(format!("{}{}_{}", prefix, name, bone)));
+        let thumb = ["meta", "a", "b"].map(|bone| Some(format!("{}thumb_{}", prefix, bone)));
+
+        HandJointNames {
+            wrist: format!("{}Wrist", prefix),
+            digits: [
+                [None, thumb[0].clone(), thumb[1].clone(), thumb[2].clone()],
+                digit("index"),
+                digit("middle"),
+                digit("ring"),
+                digit("pinky"),
+            ],
+        }
+    }
+}
+
+/// Skinned hand model driven by the tracked hand of `hand_type`.
+#[derive(Component, Clone, Debug)]
+pub struct RiggedHand {
+    pub hand_type: MyHandType,
+    pub joint_names: HandJointNames,
+    /// Scene units per model unit; glTF models are usually in meters and the scene is in millimeters.
+    pub model_scale: f32,
+    /// Rotation from the palm basis of the tracking data to the model's wrist joint.
+    pub wrist_rotation_offset: Quat,
+    /// Rotation from the tracking data's bone basis, looking along `-Z`, to the model's joints.
+    /// The default suits joints pointing along `Y`, like bones exported from Blender.
+    pub bone_rotation_offset: Quat,
+    /// Whether joints are moved to match lengths of the tracked bones, instead of keeping the model's proportions.
+    pub scale_bones: bool,
+}
+
+impl RiggedHand {
+    pub fn new(hand_type: MyHandType) -> Self {
+        let prefix = match hand_type {
+            MyHandType::Left => "L_",
+            MyHandType::Right => "R_",
+        };
+
+        RiggedHand {
+            hand_type,
+            joint_names: HandJointNames::with_prefix(prefix),
+            model_scale: 1000.,
+            wrist_rotation_offset: Quat::from_rotation_x(-FRAC_PI_2),
+            bone_rotation_offset: Quat::from_rotation_x(-FRAC_PI_2),
+            scale_bones: false,
+        }
+    }
+}
+
+/// Joints of a loaded [`RiggedHand`], with translations of the digit joints in the model's rest pose.
+#[derive(Component)]
+pub struct RiggedHandJoints {
+    pub wrist: Entity,
+    pub digits: [[Option<(Entity, Vec3)>; 4]; 5],
+}
+
+/// Materials of a loaded [`RiggedHand`], with alphas and alpha modes of the model's materials they were copied from.
+#[derive(Component)]
+pub struct RiggedHandMaterials {
+    pub materials: Vec<(Handle<StandardMaterial>, f32, AlphaMode)>,
+}
+
+fn find_descendant(
+    entity: Entity,
+    name: &str,
+    children_query: &Query<&Children>,
+    names_query: &Query<&Name>,
+) -> Option<Entity> {
+    for child in children_query.get(entity).into_iter().flatten() {
+        if matches!(names_query.get(*child), Ok(child_name) if child_name.as_str() == name) {
+            return Some(*child);
+        }
+        if let Some(descendant) = find_descendant(*child, name, children_query, names_query) {
+            return Some(descendant);
+        }
+    }
+
+    None
+}
+
+fn collect_descendants(entity: Entity, children_query: &Query<&Children>, descendants: &mut Vec<Entity>) {
+    for child in children_query.get(entity).into_iter().flatten() {
+        descendants.push(*child);
+        collect_descendants(*child, children_query, descendants);
+    }
+}
+
+/// Replaces materials of meshes under the `root` with copies, so they can fade out without affecting other hands.
+fn copy_materials(
+    root: Entity,
+    children_query: &Query<&Children>,
+    material_handles_query: &mut Query<&mut Handle<StandardMaterial>>,
+    materials: &mut Assets<StandardMaterial>,
+) -> RiggedHandMaterials {
+    let mut descendants = Vec::new();
+    collect_descendants(root, children_query, &mut descendants);
+
+    // meshes sharing a material share its copy as well
+    let mut copies = HashMap::<Handle<StandardMaterial>, Handle<StandardMaterial>>::default();
+    let mut hand_materials = Vec::new();
+    for entity in descendants {
+        let mut handle = match material_handles_query.get_mut(entity) {
+            Ok(handle) => handle,
+            Err(_) => continue,
+        };
+        if let Some(copy) = copies.get(&*handle) {
+            *handle = copy.clone();
+            continue;
+        }
+        let material = match materials.get(&*handle) {
+            None => continue,
+            Some(material) => material.clone(),
+        };
+
+        let (base_alpha, base_alpha_mode) = (material.base_color.a(), material.alpha_mode);
+        let copy = materials.add(material);
+        hand_materials.push((copy.clone(), base_alpha, base_alpha_mode));
+        copies.insert(handle.clone(), copy.clone());
+        *handle = copy;
+    }
+
+    RiggedHandMaterials {
+        materials: hand_materials,
+    }
+}
+
+/// Looks for joints of rigged hands, until their scenes are spawned. Bound hands are moved under [`HandsOrigin`].
+#[allow(clippy::too_many_arguments)]
+fn bind_rigged_hands(
+    mut commands: Commands,
+    rigged_hands_query: Query<(Entity, &RiggedHand), Without<RiggedHandJoints>>,
+    hands_origin_query: Query<Entity, With<HandsOrigin>>,
+    children_query: Query<&Children>,
+    names_query: Query<&Name>,
+    transforms_query: Query<&Transform>,
+    mut material_handles_query: Query<&mut Handle<StandardMaterial>>,
+    mut materials: Option<ResMut<Assets<StandardMaterial>>>,
+) {
+    for (entity, rigged_hand) in rigged_hands_query.iter() {
+        let find = |name: &str| find_descendant(entity, name, &children_query, &names_query);
+        let wrist = match find(&rigged_hand.joint_names.wrist) {
+            // the scene isn't spawned yet
+            None => continue,
+            Some(wrist) => wrist,
+        };
+
+        let digits = rigged_hand.joint_names.digits.clone().map(|bones| {
+            bones.map(|name| {
+                let joint = find(name.as_deref()?)?;
+                let rest_translation = transforms_query.get(joint).map_or(Vec3::ZERO, |t| t.translation);
+                Some((joint, rest_translation))
+            })
+        });
+
+        commands.entity(entity).insert(RiggedHandJoints { wrist, digits });
+        if let Some(materials) = &mut materials {
+            let hand_materials = copy_materials(entity, &children_query, &mut material_handles_query, materials);
+            commands.entity(entity).insert(hand_materials);
+        }
+        if let Ok(hands_origin) = hands_origin_query.get_single() {
+            commands.entity(hands_origin).add_child(entity);
+        }
+    }
+}
+
+/// Transform of the entity relative to the `root`, composed from local transforms of its ancestors.
+fn transform_in_root(entity: Entity, root: Entity, query: &Query<(&mut Transform, Option<&Parent>)>) -> Transform {
+    let mut transform = Transform::IDENTITY;
+    let mut current = entity;
+
+    while current != root {
+        match query.get(current) {
+            Ok((local, Some(parent))) => {
+                transform = local.mul_transform(transform);
+                current = parent.get();
+            }
+            _ => break,
+        }
+    }
+
+    transform
+}
+
+/// Sets joints of rigged hands; the model's root stays in the origin of the tracking device space, so joints get
+/// positions and rotations of the tracking data directly, scaled by [`RiggedHand::model_scale`].
+#[allow(clippy::type_complexity)]
+fn update_rigged_hands(
+    hands_data: Res<HandsData>,
+    hand_style: Option<Res<HandStyle>>,
+    mut rigged_hands_query: Query<(Entity, &RiggedHand, &RiggedHandJoints, &mut Visibility)>,
+    mut joints_query: Query<(&mut Transform, Option<&Parent>)>,
+) {
+    let is_style_selected = match &hand_style {
+        None => true,
+        Some(style) => **style == HandStyle::Rigged,
+    };
+
+    for (root, rigged_hand, joints, mut visibility) in rigged_hands_query.iter_mut() {
+        let hand = hands_data.hands.iter().find(|hand| hand.type_ == rigged_hand.hand_type);
+        visibility.is_visible = hand.is_some() && is_style_selected;
+
+        let hand = match hand {
+            Some(hand) if visibility.is_visible => hand,
+            _ => continue,
+        };
+
+        if let Ok((mut root_transform, _)) = joints_query.get_mut(root) {
+            *root_transform = Transform::from_scale(Vec3::splat(rigged_hand.model_scale));
+        }
+
+        // wrist gets both the position and the rotation; the root is scaled, so positions are in model units
+        let wrist_parent = transform_in_root(
+            joints_query
+                .get(joints.wrist)
+                .ok()
+                .and_then(|(_, parent)| parent)
+                .map_or(root, |parent| parent.get()),
+            root,
+            &joints_query,
+        );
+        let wrist_target = Transform {
+            translation: hand.arm.next_joint / rigged_hand.model_scale,
+            rotation: Quat::from_vec4(hand.palm.orientation) * rigged_hand.wrist_rotation_offset,
+            scale: Vec3::ONE,
+        };
+        let wrist_local =
+            Transform::from_matrix(wrist_parent.compute_matrix().inverse() * wrist_target.compute_matrix());
+        if let Ok((mut wrist_transform, _)) = joints_query.get_mut(joints.wrist) {
+            wrist_transform.translation = wrist_local.translation;
+            wrist_transform.rotation = wrist_local.rotation;
+        }
+
+        // digit joints only rotate, parents are updated before their children
+        for (digit, digit_joints) in hand.digits.iter().zip(joints.digits.iter()) {
+            let bones = digit.bones();
+
+            for (bone_index, joint) in digit_joints.iter().enumerate() {
+                let (joint, rest_translation) = match joint {
+                    None => continue,
+                    Some(joint) => *joint,
+                };
+                let parent = match joints_query.get(joint) {
+                    Ok((_, Some(parent))) => parent.get(),
+                    _ => continue,
+                };
+
+                let parent_transform = transform_in_root(parent, root, &joints_query);
+                let rotation =
+                    parent_transform.rotation.inverse() * bones[bone_index].rotation * rigged_hand.bone_rotation_offset;
+
+                // the joint sits at the end of the previous bone, unless the model has no joint for it
+                let follows_joint = bone_index > 0 && digit_joints[bone_index - 1].is_some();
+                let translation = match bone_index {
+                    1..=3 if rigged_hand.scale_bones && follows_joint => {
+                        let length =
+                            bones[bone_index - 1].length() / rigged_hand.model_scale / parent_transform.scale.x;
+                        rest_translation.normalize_or_zero() * length
+                    }
+                    _ => rest_translation,
+                };
+
+                if let Ok((mut transform, _)) = joints_query.get_mut(joint) {
+                    transform.rotation = rotation;
+                    transform.translation = translation;
+                }
+            }
+        }
+    }
+}
+
+fn update_rigged_hands_opacity(
+    opacity: Res<HandOpacity>,
+    rigged_hands_query: Query<(&RiggedHand, &RiggedHandMaterials, ChangeTrackers<RiggedHandMaterials>)>,
+    materials: Option<ResMut<Assets<StandardMaterial>>>,
+) {
+    let mut materials = match materials {
+        None => return,
+        Some(materials) => materials,
+    };
+
+    for (rigged_hand, hand_materials, materials_tracker) in rigged_hands_query.iter() {
+        if !opacity.is_changed() && !materials_tracker.is_added() {
+            continue;
+        }
+
+        let hand_opacity = opacity.get(rigged_hand.hand_type);
+        for (handle, base_alpha, base_alpha_mode) in hand_materials.materials.iter() {
+            set_material_opacity(&mut materials, handle, hand_opacity, *base_alpha, *base_alpha_mode);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+
+use crate::hand_model::{HandPose, HandSkeleton};
+use crate::leap_controller_plugin::{update_bones_transforms, update_hand_data, HandsData, MyHandType};
+
+const TOGGLE_KEY: KeyCode = KeyCode::F2;
+const SWITCH_HAND_TYPE_KEY: KeyCode = KeyCode::T;
+const PINCH_KEY: KeyCode = KeyCode::P;
+const FIST_KEY: KeyCode = KeyCode::G;
+const RAISE_KEY: KeyCode = KeyCode::E;
+const LOWER_KEY: KeyCode = KeyCode::Q;
+
+/// Keys curling the thumb, index, middle, ring and pinky fingers.
+const CURL_KEYS: [KeyCode; 5] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+];
+
+/// Size of the area above the device (along `X` and `Z`) the window is mapped to.
+const CURSOR_RANGE: Vec2 = Vec2::new(400., 300.);
+
+const MIN_HEIGHT: f32 = 50.;
+const MAX_HEIGHT: f32 = 500.;
+const HEIGHT_SPEED: f32 = 200.;
+
+/// Curl and pinch change per second, so fingers move instead of jumping between poses.
+const CURL_SPEED: f32 = 4.;
+
+/// Hand built from keyboard and mouse input, which replaces tracked hands when enabled.
+/// Mouse moves the hand above the device, `E`/`Q` raise and lower it, `1`-`5` curl fingers,
+/// `P` pinches, `G` makes a fist, `T` switches between the left and the right hand and `F2` toggles it.
+pub struct SyntheticHandPlugin;
+
+impl Plugin for SyntheticHandPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SyntheticHand>()
+            .add_system(control_synthetic_hand)
+            .add_system(
+                update_synthetic_hand_data
+                    .after(control_synthetic_hand)
+                    .after(update_hand_data)
+                    .before(update_bones_transforms),
+            );
+    }
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct SyntheticHand {
+    pub enabled: bool,
+    pub type_: MyHandType,
+    pub skeleton: HandSkeleton,
+    /// Palm position in the tracking device space.
+    pub position: Vec3,
+    /// From 0 (extended) to 1 (fully curled), in the order of [`MyHand::digits`].
+    pub curls: [f32; 5],
+    /// From 0 to 1; blends the curled fingers into [`HandPose::pinch`].
+    pub pinch: f32,
+    visible_time: u64,
+}
+
+impl Default for SyntheticHand {
+    fn default() -> Self {
+        SyntheticHand {
+            enabled: false,
+            type_: MyHandType::Right,
+            skeleton: HandSkeleton::default(),
+            position: Vec3::new(0., 200., 0.),
+            curls: [0.; 5],
+            pinch: 0.,
+            visible_time: 0,
+        }
+    }
+}
+
+fn control_synthetic_hand(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    mut synthetic_hand: ResMut<SyntheticHand>,
+) {
+    if keys.just_pressed(TOGGLE_KEY) {
+        synthetic_hand.enabled = !synthetic_hand.enabled;
+        info!("Synthetic hand enabled: {}", synthetic_hand.enabled);
+    }
+
+    if !synthetic_hand.enabled {
+        return;
+    }
+
+    if keys.just_pressed(SWITCH_HAND_TYPE_KEY) {
+        synthetic_hand.type_ = match synthetic_hand.type_ {
+            MyHandType::Left => MyHandType::Right,
+            MyHandType::Right => MyHandType::Left,
+        };
+    }
+
+    let dt = time.delta_seconds();
+
+    if let Some(window) = windows.get_primary() {
+        if let Some(cursor_position) = window.cursor_position() {
+            let size = Vec2::new(window.width(), window.height());
+            let offset = (cursor_position / size - Vec2::splat(0.5)) * CURSOR_RANGE;
+
+            // moving the cursor up moves the hand away from the user
+            synthetic_hand.position.x = offset.x;
+            synthetic_hand.position.z = -offset.y;
+        }
+    }
+
+    let height_direction = keys.pressed(RAISE_KEY) as i8 as f32 - keys.pressed(LOWER_KEY) as i8 as f32;
+    synthetic_hand.position.y =
+        (synthetic_hand.position.y + height_direction * HEIGHT_SPEED * dt).clamp(MIN_HEIGHT, MAX_HEIGHT);
+
+    let approach = |value: f32, target: f32| {
+        let step = CURL_SPEED * dt;
+        value + (target - value).clamp(-step, step)
+    };
+
+    let is_fist = keys.pressed(FIST_KEY);
+    for (index, key) in CURL_KEYS.iter().enumerate() {
+        let target = if is_fist || keys.pressed(*key) { 1. } else { 0. };
+        synthetic_hand.curls[index] = approach(synthetic_hand.curls[index], target);
+    }
+
+    let pinch_target = if keys.pressed(PINCH_KEY) { 1. } else { 0. };
+    synthetic_hand.pinch = approach(synthetic_hand.pinch, pinch_target);
+}
+
+fn update_synthetic_hand_data(
+    time: Res<Time>,
+    mut synthetic_hand: ResMut<SyntheticHand>,
+    mut hands_data: ResMut<HandsData>,
+    mut previous_position: Local<Option<Vec3>>,
+) {
+    if !synthetic_hand.enabled {
+        if previous_position.take().is_some() {
+            hands_data.hands.clear();
+        }
+        return;
+    }
+
+    let dt = time.delta_seconds();
+    let velocity = match previous_position.replace(synthetic_hand.position) {
+        Some(previous_position) if dt > 0. => (synthetic_hand.position - previous_position) / dt,
+        _ => Vec3::ZERO,
+    };
+
+    synthetic_hand.visible_time += time.delta().as_micros() as u64;
+
+    let pose = HandPose::curled(synthetic_hand.curls).lerp(&HandPose::pinch(), synthetic_hand.pinch);
+    let mut hand = synthetic_hand.skeleton.solve(
+        synthetic_hand.type_,
+        &pose,
+        &Transform::from_translation(synthetic_hand.position),
+    );
+    hand.visible_time = synthetic_hand.visible_time;
+    hand.palm.velocity = velocity;
+    hands_data.hands = vec![hand];
+}
@@ -0,0 +1,77 @@
+use bevy::diagnostic::{Diagnostic, DiagnosticId, Diagnostics};
+use bevy::prelude::*;
+
+use crate::leap_controller_plugin::{update_hand_data, HeldHands, TrackingAnomalies, TrackingFrame};
+
+/// Adds tracking diagnostics, based on [`TrackingFrame`], [`TrackingAnomalies`] and [`HeldHands`]: "tracking_fps",
+/// "tracking_frame_age", "tracking_latency", "tracking_dropped_frames", "tracking_anomalies",
+/// "tracking_rejected_hands", "tracking_repaired_hands" and "tracking_held_hands".
+pub struct TrackingDiagnosticsPlugin;
+
+impl Plugin for TrackingDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(Self::setup_system)
+            .add_system(Self::diagnostic_system.after(update_hand_data));
+    }
+}
+
+impl TrackingDiagnosticsPlugin {
+    pub const TRACKING_FPS: DiagnosticId = DiagnosticId::from_u128(81403318670047627736258051107143120821);
+    /// Time since the latest tracking frame was received.
+    pub const FRAME_AGE: DiagnosticId = DiagnosticId::from_u128(323132474312304204922929580534521671613);
+    /// Latency above the lowest one observed so far. Clocks of the tracking service and the app aren't
+    /// synchronized, so only changes of the latency can be measured.
+    pub const LATENCY: DiagnosticId = DiagnosticId::from_u128(154873940280005858938147965836056483068);
+    pub const DROPPED_FRAMES: DiagnosticId = DiagnosticId::from_u128(131566407101478846823542445114079995225);
+    /// Anomalies found in incoming hands since the startup, see [`TrackingAnomalies::total`].
+    pub const ANOMALIES: DiagnosticId = DiagnosticId::from_u128(135095242311941232928373329476099214894);
+    pub const REJECTED_HANDS: DiagnosticId = DiagnosticId::from_u128(2869994114643333791554231720854896878);
+    pub const REPAIRED_HANDS: DiagnosticId = DiagnosticId::from_u128(171467366818228882955666045847592028996);
+    /// Lost hands which are still in [`crate::leap_controller_plugin::HandsData`] with their last pose.
+    pub const HELD_HANDS: DiagnosticId = DiagnosticId::from_u128(245310982257390516044871238975043619307);
+
+    pub fn setup_system(mut diagnostics: ResMut<Diagnostics>) {
+        diagnostics.add(Diagnostic::new(Self::TRACKING_FPS, "tracking_fps", 20));
+        diagnostics.add(Diagnostic::new(Self::FRAME_AGE, "tracking_frame_age", 20).with_suffix("ms"));
+        diagnostics.add(Diagnostic::new(Self::LATENCY, "tracking_latency", 20).with_suffix("ms"));
+        diagnostics.add(Diagnostic::new(Self::DROPPED_FRAMES, "tracking_dropped_frames", 1).with_smoothing_factor(0.));
+        diagnostics.add(Diagnostic::new(Self::ANOMALIES, "tracking_anomalies", 1).with_smoothing_factor(0.));
+        diagnostics.add(Diagnostic::new(Self::REJECTED_HANDS, "tracking_rejected_hands", 1).with_smoothing_factor(0.));
+        diagnostics.add(Diagnostic::new(Self::REPAIRED_HANDS, "tracking_repaired_hands", 1).with_smoothing_factor(0.));
+        diagnostics.add(Diagnostic::new(Self::HELD_HANDS, "tracking_held_hands", 1).with_smoothing_factor(0.));
+    }
+
+    pub fn diagnostic_system(
+        mut diagnostics: ResMut<Diagnostics>,
+        time: Res<Time>,
+        tracking_frame: Res<TrackingFrame>,
+        anomalies: Res<TrackingAnomalies>,
+        held_hands: Res<HeldHands>,
+        mut min_clock_offset: Local<Option<f64>>,
+    ) {
+        let received_at = match tracking_frame.received_at {
+            None => return,
+            Some(received_at) => received_at,
+        };
+
+        diagnostics.add_measurement(Self::FRAME_AGE, || (time.elapsed_seconds_f64() - received_at) * 1000.);
+        // hands are dropped at the end of the hold also between tracking frames
+        diagnostics.add_measurement(Self::HELD_HANDS, || held_hands.hand_types().count() as f64);
+
+        if !tracking_frame.is_changed() {
+            return;
+        }
+
+        // difference between the app time and the frame timestamp; the smallest one had the lowest latency
+        let clock_offset = received_at - tracking_frame.timestamp as f64 / 1_000_000.;
+        let min_offset = min_clock_offset.map_or(clock_offset, |offset: f64| offset.min(clock_offset));
+        *min_clock_offset = Some(min_offset);
+
+        diagnostics.add_measurement(Self::TRACKING_FPS, || tracking_frame.framerate as f64);
+        diagnostics.add_measurement(Self::LATENCY, || (clock_offset - min_offset) * 1000.);
+        diagnostics.add_measurement(Self::DROPPED_FRAMES, || tracking_frame.dropped_frames as f64);
+        diagnostics.add_measurement(Self::ANOMALIES, || anomalies.total() as f64);
+        diagnostics.add_measurement(Self::REJECTED_HANDS, || anomalies.rejected_hands as f64);
+        diagnostics.add_measurement(Self::REPAIRED_HANDS, || anomalies.repaired_hands as f64);
+    }
+}
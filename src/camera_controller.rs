@@ -0,0 +1,263 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+
+use leap_input::hand_query::HandQuery;
+use leap_input::leap_controller_plugin::MyHandType;
+
+use crate::grab_gesture::{GrabData, GrabSource};
+use crate::pinch_gesture::{pinch_point, PINCH_END_STRENGTH, PINCH_START_STRENGTH};
+use crate::PlayerCamera;
+
+const SWITCH_MODE_KEY: KeyCode = KeyCode::C;
+
+/// Radians per pixel of mouse motion.
+const MOUSE_ROTATION_SENSITIVITY: f32 = 0.005;
+
+/// Radians per second of a pressed arrow key.
+const KEYS_ROTATION_SPEED: f32 = 1.5;
+
+/// Scene units per second of a pressed movement key.
+const KEYS_MOVEMENT_SPEED: f32 = 400.;
+
+/// Part of the distance to the focus point changed by one scroll line.
+const SCROLL_ZOOM_FACTOR: f32 = 0.1;
+
+/// Radians per scene unit of the hands motion.
+const HANDS_ROTATION_SENSITIVITY: f32 = 0.006;
+
+/// Change of the distance to the focus point per change of the distance between pinching hands.
+const HANDS_ZOOM_SENSITIVITY: f32 = 2.;
+
+const MIN_DISTANCE: f32 = 50.;
+const MAX_DISTANCE: f32 = 5000.;
+
+/// Pitch is limited, so the camera never turns upside down.
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.05;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Camera circles around the focus point. Dragging with the right mouse button (or arrows) orbits,
+    /// the middle mouse button (or `WASD`) pans and scrolling zooms.
+    #[default]
+    Orbit,
+    /// Camera turns in place when dragging with the right mouse button (or arrows);
+    /// `WASD` moves it along the view direction, `Space` and `LShift` up and down.
+    Fly,
+}
+
+/// State of [`PlayerCamera`]. Camera looks at `focus` from `distance`, rotated by `yaw` and `pitch`.
+#[derive(Resource, Clone, Debug)]
+pub struct CameraRig {
+    pub mode: CameraMode,
+    pub focus: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+}
+
+impl CameraRig {
+    pub fn looking_at(eye: Vec3, focus: Vec3) -> Self {
+        let offset = eye - focus;
+        let distance = offset.length();
+        let horizontal_distance = Vec2::new(offset.x, offset.z).length();
+
+        CameraRig {
+            mode: CameraMode::default(),
+            focus,
+            yaw: offset.x.atan2(offset.z),
+            pitch: -offset.y.atan2(horizontal_distance),
+            distance,
+        }
+    }
+
+    pub fn rotation(&self) -> Quat {
+        Quat::from_euler(EulerRot::YXZ, self.yaw, self.pitch, 0.)
+    }
+
+    pub fn transform(&self) -> Transform {
+        let rotation = self.rotation();
+
+        Transform {
+            translation: self.focus + rotation * Vec3::Z * self.distance,
+            rotation,
+            ..default()
+        }
+    }
+
+    /// Orbits around the focus point or, in the fly mode, turns the camera in place.
+    pub fn rotate(&mut self, delta_yaw: f32, delta_pitch: f32) {
+        let eye = self.transform().translation;
+
+        self.yaw += delta_yaw;
+        self.pitch = (self.pitch + delta_pitch).clamp(-MAX_PITCH, MAX_PITCH);
+
+        if self.mode == CameraMode::Fly {
+            self.focus = eye - self.rotation() * Vec3::Z * self.distance;
+        }
+    }
+
+    /// Moves the camera together with its focus point; `delta` is given in the camera space.
+    pub fn translate(&mut self, delta: Vec3) {
+        self.focus += self.rotation() * delta;
+    }
+
+    /// Positive `amount` moves the camera closer to the focus point, or forward in the fly mode.
+    pub fn zoom(&mut self, amount: f32) {
+        match self.mode {
+            CameraMode::Orbit => {
+                self.distance = (self.distance - amount).clamp(MIN_DISTANCE, MAX_DISTANCE);
+            }
+            CameraMode::Fly => self.translate(Vec3::NEG_Z * amount),
+        }
+    }
+}
+
+/// Controls [`PlayerCamera`] with mouse, keyboard and two-hand gestures.
+/// [`CameraRig`] has to be inserted when the camera is spawned.
+pub struct CameraControllerPlugin;
+
+impl Plugin for CameraControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(switch_camera_mode)
+            .add_system(control_camera_with_mouse_and_keys.after(switch_camera_mode))
+            .add_system(control_camera_with_hands.after(switch_camera_mode))
+            .add_system(
+                update_camera_transform
+                    .after(control_camera_with_mouse_and_keys)
+                    .after(control_camera_with_hands),
+            );
+    }
+}
+
+fn switch_camera_mode(keys: Res<Input<KeyCode>>, mut rig: ResMut<CameraRig>) {
+    if !keys.just_pressed(SWITCH_MODE_KEY) {
+        return;
+    }
+
+    rig.mode = match rig.mode {
+        CameraMode::Orbit => CameraMode::Fly,
+        CameraMode::Fly => CameraMode::Orbit,
+    };
+    info!("Camera mode: {:?}", rig.mode);
+}
+
+fn control_camera_with_mouse_and_keys(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    grab_res: Res<GrabData>,
+    mut rig: ResMut<CameraRig>,
+) {
+    let mouse_delta = mouse_motion.iter().map(|motion| motion.delta).sum::<Vec2>();
+    let scroll = mouse_wheel
+        .iter()
+        .map(|wheel| match wheel.unit {
+            MouseScrollUnit::Line => wheel.y,
+            // roughly the height of a line
+            MouseScrollUnit::Pixel => wheel.y / 20.,
+        })
+        .sum::<f32>();
+
+    let dt = time.delta_seconds();
+    let axis = |positive: KeyCode, negative: KeyCode| {
+        keys.pressed(positive) as i8 as f32 - keys.pressed(negative) as i8 as f32
+    };
+
+    // rotation
+    let mut rotation =
+        Vec2::new(axis(KeyCode::Left, KeyCode::Right), axis(KeyCode::Up, KeyCode::Down)) * KEYS_ROTATION_SPEED * dt;
+    if mouse_buttons.pressed(MouseButton::Right) {
+        rotation -= mouse_delta * MOUSE_ROTATION_SENSITIVITY;
+    }
+    if rotation != Vec2::ZERO {
+        rig.rotate(rotation.x, rotation.y);
+    }
+
+    // movement
+    let keys_movement = match rig.mode {
+        CameraMode::Orbit => Vec3::new(axis(KeyCode::D, KeyCode::A), axis(KeyCode::W, KeyCode::S), 0.),
+        CameraMode::Fly => Vec3::new(
+            axis(KeyCode::D, KeyCode::A),
+            axis(KeyCode::Space, KeyCode::LShift),
+            axis(KeyCode::S, KeyCode::W),
+        ),
+    };
+    let mut movement = keys_movement * KEYS_MOVEMENT_SPEED * dt;
+    if mouse_buttons.pressed(MouseButton::Middle) {
+        // dragged point stays under the cursor, more or less
+        let units_per_pixel = rig.distance / 1000.;
+        movement += Vec3::new(-mouse_delta.x, mouse_delta.y, 0.) * units_per_pixel;
+    }
+    if movement != Vec3::ZERO {
+        rig.translate(movement);
+    }
+
+    // zoom; while an object is dragged with the mouse, scrolling moves the object instead
+    if scroll != 0. && !matches!(grab_res.source(), GrabSource::Mouse) {
+        let amount = rig.distance * SCROLL_ZOOM_FACTOR * scroll;
+        rig.zoom(amount);
+    }
+}
+
+/// Pinch points of both hands, in the tracking device space.
+/// Using the device space keeps the gesture independent of the camera, which the hands may follow.
+#[derive(Default)]
+pub struct TwoHandPinch {
+    last_points: Option<(Vec3, Vec3)>,
+}
+
+/// Moving both pinching hands together orbits the camera, spreading them apart zooms in.
+/// Like other gestures, it's started only by hands which can interact, see [`HandQuery::can_interact`].
+pub fn control_camera_with_hands(
+    grab_res: Res<GrabData>,
+    hand_query: HandQuery,
+    mut pinch: Local<TwoHandPinch>,
+    mut rig: ResMut<CameraRig>,
+) {
+    let is_pinching = |hand_type: MyHandType| match (hand_query.hand(hand_type), pinch.last_points) {
+        (None, _) => false,
+        (Some(hand), None) => hand.pinch_strength >= PINCH_START_STRENGTH && hand_query.can_interact(hand_type),
+        (Some(hand), Some(_)) => hand.pinch_strength >= PINCH_END_STRENGTH,
+    };
+
+    let both_pinching = is_pinching(MyHandType::Left) && is_pinching(MyHandType::Right);
+
+    // a pinch holding an object is not a camera gesture
+    if !both_pinching || grab_res.current_entity().is_some() {
+        pinch.last_points = None;
+        return;
+    }
+
+    let (left_hand, right_hand) = match (hand_query.hand(MyHandType::Left), hand_query.hand(MyHandType::Right)) {
+        (Some(left_hand), Some(right_hand)) => (left_hand, right_hand),
+        _ => return,
+    };
+    let points = (pinch_point(left_hand), pinch_point(right_hand));
+    let (last_first, last_second) = match pinch.last_points.replace(points) {
+        None => return,
+        Some(last_points) => last_points,
+    };
+
+    let center_delta = (points.0 + points.1 - last_first - last_second) / 2.;
+    let spread_delta = points.0.distance(points.1) - last_first.distance(last_second);
+
+    rig.rotate(
+        -center_delta.x * HANDS_ROTATION_SENSITIVITY,
+        center_delta.y * HANDS_ROTATION_SENSITIVITY,
+    );
+    rig.zoom(spread_delta * HANDS_ZOOM_SENSITIVITY);
+}
+
+pub fn update_camera_transform(rig: Res<CameraRig>, mut camera_query: Query<&mut Transform, With<PlayerCamera>>) {
+    if !rig.is_changed() {
+        return;
+    }
+
+    for mut transform in camera_query.iter_mut() {
+        *transform = rig.transform();
+    }
+}
@@ -0,0 +1,547 @@
+use bevy::prelude::*;
+
+use crate::constraints::snap_released_obj;
+use crate::grab_gesture::{detect_obj_grabbing, GrabData, GrabEvent, update_grabbed_obj_transform};
+
+/// Gravity in scene units (millimeters) per second squared.
+pub const GRAVITY: Vec3 = Vec3::new(0., -9810., 0.);
+
+/// Number of passes over all contacts in a single frame.
+/// More passes let stacked objects settle faster.
+const SOLVER_ITERATIONS: usize = 4;
+
+const EPSILON: f32 = 1e-6;
+
+/// Simple collision handling used when the `physics` feature is disabled.
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(resolve_grabbed_obj_collisions.after(update_grabbed_obj_transform))
+            .add_system(update_grabbed_bodies.after(detect_obj_grabbing))
+            .add_system(
+                simulate_released_bodies
+                    .after(update_grabbed_bodies)
+                    .after(snap_released_obj)
+                    .after(resolve_grabbed_obj_collisions),
+            );
+    }
+}
+
+/// Shape used for collision detection. Entity's scale is not taken into account.
+#[derive(Component, Reflect, FromReflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+pub enum CollisionShape {
+    Sphere {
+        radius: f32,
+    },
+    /// Box oriented by the entity's rotation.
+    Box {
+        half_extents: Vec3,
+    },
+    /// Capsule aligned with the entity's local `Y` axis.
+    /// `half_height` is the half of the distance between hemispheres' centers.
+    Capsule {
+        radius: f32,
+        half_height: f32,
+    },
+}
+
+impl Default for CollisionShape {
+    fn default() -> Self {
+        CollisionShape::Sphere { radius: 0. }
+    }
+}
+
+/// Marks colliders which never move, e.g. the table.
+#[derive(Component)]
+pub struct StaticCollider;
+
+/// Grabbable object which falls and rests on other colliders after being released.
+/// Sleeping bodies are not simulated until they are grabbed and released.
+#[derive(Component, Default)]
+pub struct Body {
+    pub velocity: Vec3,
+    pub sleeping: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+    /// Direction in which the first collider has to be moved to separate the colliders.
+    pub normal: Vec3,
+    pub depth: f32,
+}
+
+/// Shape reduced to a core (segment or oriented box) and a radius around it.
+enum Core {
+    Segment {
+        start: Vec3,
+        end: Vec3,
+        radius: f32,
+    },
+    Box {
+        center: Vec3,
+        rotation: Quat,
+        half_extents: Vec3,
+    },
+}
+
+impl CollisionShape {
+    fn core(&self, transform: &Transform) -> Core {
+        match *self {
+            CollisionShape::Sphere { radius } => Core::Segment {
+                start: transform.translation,
+                end: transform.translation,
+                radius,
+            },
+            CollisionShape::Capsule { radius, half_height } => {
+                let half_segment = transform.rotation * Vec3::Y * half_height;
+                Core::Segment {
+                    start: transform.translation - half_segment,
+                    end: transform.translation + half_segment,
+                    radius,
+                }
+            }
+            CollisionShape::Box { half_extents } => Core::Box {
+                center: transform.translation,
+                rotation: transform.rotation,
+                half_extents,
+            },
+        }
+    }
+}
+
+pub fn contact(
+    a: &CollisionShape,
+    a_transform: &Transform,
+    b: &CollisionShape,
+    b_transform: &Transform,
+) -> Option<Contact> {
+    match (a.core(a_transform), b.core(b_transform)) {
+        (
+            Core::Segment { start, end, radius },
+            Core::Segment {
+                start: b_start,
+                end: b_end,
+                radius: b_radius,
+            },
+        ) => {
+            let (a_point, b_point) = closest_points_between_segments(start, end, b_start, b_end);
+            spheres_contact(a_point, radius, b_point, b_radius)
+        }
+        (
+            Core::Segment { start, end, radius },
+            Core::Box {
+                center,
+                rotation,
+                half_extents,
+            },
+        ) => segment_box_contact(start, end, radius, center, rotation, half_extents),
+        (
+            Core::Box {
+                center,
+                rotation,
+                half_extents,
+            },
+            Core::Segment { start, end, radius },
+        ) => segment_box_contact(start, end, radius, center, rotation, half_extents).map(|contact| Contact {
+            normal: -contact.normal,
+            depth: contact.depth,
+        }),
+        (
+            Core::Box {
+                center,
+                rotation,
+                half_extents,
+            },
+            Core::Box {
+                center: b_center,
+                rotation: b_rotation,
+                half_extents: b_half_extents,
+            },
+        ) => boxes_contact(center, rotation, half_extents, b_center, b_rotation, b_half_extents),
+    }
+}
+
+fn closest_point_on_segment(start: Vec3, end: Vec3, point: Vec3) -> Vec3 {
+    let segment = end - start;
+    let length_squared = segment.length_squared();
+    if length_squared <= EPSILON {
+        return start;
+    }
+
+    start + segment * ((point - start).dot(segment) / length_squared).clamp(0., 1.)
+}
+
+/// Based on "Real-Time Collision Detection" by Christer Ericson, chapter 5.1.9
+fn closest_points_between_segments(p1: Vec3, q1: Vec3, p2: Vec3, q2: Vec3) -> (Vec3, Vec3) {
+    let d1 = q1 - p1;
+    let d2 = q2 - p2;
+    let r = p1 - p2;
+    let a = d1.length_squared();
+    let e = d2.length_squared();
+    let f = d2.dot(r);
+
+    if a <= EPSILON && e <= EPSILON {
+        return (p1, p2);
+    }
+
+    let (s, t) = if a <= EPSILON {
+        (0., (f / e).clamp(0., 1.))
+    } else {
+        let c = d1.dot(r);
+        if e <= EPSILON {
+            ((-c / a).clamp(0., 1.), 0.)
+        } else {
+            let b = d1.dot(d2);
+            let denominator = a * e - b * b;
+            let s = if denominator != 0. {
+                ((b * f - c * e) / denominator).clamp(0., 1.)
+            } else {
+                0.
+            };
+            let t = (b * s + f) / e;
+
+            if t < 0. {
+                ((-c / a).clamp(0., 1.), 0.)
+            } else if t > 1. {
+                (((b - c) / a).clamp(0., 1.), 1.)
+            } else {
+                (s, t)
+            }
+        }
+    };
+
+    (p1 + d1 * s, p2 + d2 * t)
+}
+
+fn spheres_contact(a_center: Vec3, a_radius: f32, b_center: Vec3, b_radius: f32) -> Option<Contact> {
+    let delta = a_center - b_center;
+    let distance = delta.length();
+    if distance >= a_radius + b_radius {
+        return None;
+    }
+
+    Some(Contact {
+        normal: if distance > EPSILON { delta / distance } else { Vec3::Y },
+        depth: a_radius + b_radius - distance,
+    })
+}
+
+fn segment_box_contact(
+    start: Vec3,
+    end: Vec3,
+    radius: f32,
+    center: Vec3,
+    rotation: Quat,
+    half_extents: Vec3,
+) -> Option<Contact> {
+    let closest_point_on_box = |point: Vec3| {
+        let local = rotation.inverse() * (point - center);
+        center + rotation * local.clamp(-half_extents, half_extents)
+    };
+
+    // a few steps of alternating projections are enough to find segment's point closest to the box
+    let mut point = closest_point_on_segment(start, end, center);
+    for _ in 0..3 {
+        point = closest_point_on_segment(start, end, closest_point_on_box(point));
+    }
+
+    sphere_box_contact(point, radius, center, rotation, half_extents)
+}
+
+fn sphere_box_contact(point: Vec3, radius: f32, center: Vec3, rotation: Quat, half_extents: Vec3) -> Option<Contact> {
+    let local = rotation.inverse() * (point - center);
+    let clamped = local.clamp(-half_extents, half_extents);
+
+    if local != clamped {
+        let delta = local - clamped;
+        let distance = delta.length();
+        if distance >= radius {
+            return None;
+        }
+
+        return Some(Contact {
+            normal: rotation * (delta / distance),
+            depth: radius - distance,
+        });
+    }
+
+    // sphere's center is inside the box; push it out through the closest face
+    let distances_to_faces = half_extents - local.abs();
+    let axis_index = if distances_to_faces.x <= distances_to_faces.y && distances_to_faces.x <= distances_to_faces.z {
+        0
+    } else if distances_to_faces.y <= distances_to_faces.z {
+        1
+    } else {
+        2
+    };
+
+    let mut local_normal = Vec3::ZERO;
+    local_normal[axis_index] = if local[axis_index] < 0. { -1. } else { 1. };
+
+    Some(Contact {
+        normal: rotation * local_normal,
+        depth: distances_to_faces[axis_index] + radius,
+    })
+}
+
+/// Separating axis test which checks only faces' normals. It is not exact for edge-to-edge contacts,
+/// but it is good enough for resting and stacking boxes.
+fn boxes_contact(
+    a_center: Vec3,
+    a_rotation: Quat,
+    a_half_extents: Vec3,
+    b_center: Vec3,
+    b_rotation: Quat,
+    b_half_extents: Vec3,
+) -> Option<Contact> {
+    let a_axes = [a_rotation * Vec3::X, a_rotation * Vec3::Y, a_rotation * Vec3::Z];
+    let b_axes = [b_rotation * Vec3::X, b_rotation * Vec3::Y, b_rotation * Vec3::Z];
+    let centers_delta = b_center - a_center;
+
+    let projected_radius = |axes: &[Vec3; 3], half_extents: Vec3, axis: Vec3| {
+        (0..3).map(|i| (half_extents[i] * axes[i].dot(axis)).abs()).sum::<f32>()
+    };
+
+    let mut best = Contact {
+        normal: Vec3::Y,
+        depth: f32::INFINITY,
+    };
+    for axis in a_axes.iter().chain(b_axes.iter()) {
+        let distance = centers_delta.dot(*axis);
+        let overlap = projected_radius(&a_axes, a_half_extents, *axis)
+            + projected_radius(&b_axes, b_half_extents, *axis)
+            - distance.abs();
+
+        if overlap <= 0. {
+            return None;
+        }
+
+        if overlap < best.depth {
+            best = Contact {
+                normal: if distance > 0. { -*axis } else { *axis },
+                depth: overlap,
+            };
+        }
+    }
+
+    Some(best)
+}
+
+/// Pushes the grabbed object and the rest of its group out of all other colliders, so they stop at surfaces.
+pub fn resolve_grabbed_obj_collisions(
+    grab_res: Res<GrabData>,
+    mut shapes_query: Query<(Entity, &mut Transform, &CollisionShape)>,
+) {
+    if grab_res.current_entity().is_none() {
+        return;
+    }
+
+    let obstacles = shapes_query
+        .iter()
+        .filter(|(entity, _, _)| !grab_res.is_held(*entity))
+        .map(|(_, transform, shape)| (*transform, *shape))
+        .collect::<Vec<_>>();
+
+    for held_entity in grab_res.held_entities() {
+        let (_, mut held_transform, held_shape) = match shapes_query.get_mut(held_entity) {
+            Err(_) => continue,
+            Ok(result) => result,
+        };
+
+        for _ in 0..SOLVER_ITERATIONS {
+            for (obstacle_transform, obstacle_shape) in obstacles.iter() {
+                if let Some(contact) = contact(held_shape, &held_transform, obstacle_shape, obstacle_transform) {
+                    held_transform.translation += contact.normal * contact.depth;
+                }
+            }
+        }
+    }
+}
+
+/// Held bodies don't keep their velocity; released ones start being simulated.
+pub fn update_grabbed_bodies(mut grab_events: EventReader<GrabEvent>, mut bodies_query: Query<&mut Body>) {
+    for event in grab_events.iter() {
+        match event {
+            GrabEvent::Started(entity) => {
+                if let Ok(mut body) = bodies_query.get_mut(*entity) {
+                    body.velocity = Vec3::ZERO;
+                }
+            }
+            GrabEvent::Ended(entity) => {
+                if let Ok(mut body) = bodies_query.get_mut(*entity) {
+                    body.sleeping = false;
+                    body.velocity = Vec3::ZERO;
+                }
+            }
+        }
+    }
+}
+
+/// Applies gravity to released bodies and resolves their resting contacts with static colliders
+/// and other bodies, so they stack instead of interpenetrating.
+#[allow(clippy::type_complexity)]
+pub fn simulate_released_bodies(
+    time: Res<Time>,
+    grab_res: Res<GrabData>,
+    mut bodies_query: Query<(Entity, &mut Transform, &CollisionShape, &mut Body)>,
+    static_query: Query<(&Transform, &CollisionShape), (With<StaticCollider>, Without<Body>)>,
+) {
+    let is_simulated = |entity: Entity, body: &Body| !body.sleeping && !grab_res.is_held(entity);
+
+    for (entity, mut transform, _, mut body) in bodies_query.iter_mut() {
+        if is_simulated(entity, &body) {
+            body.velocity += GRAVITY * time.delta_seconds();
+            transform.translation += body.velocity * time.delta_seconds();
+        }
+    }
+
+    for _ in 0..SOLVER_ITERATIONS {
+        for (entity, mut transform, shape, mut body) in bodies_query.iter_mut() {
+            if !is_simulated(entity, &body) {
+                continue;
+            }
+
+            for (static_transform, static_shape) in static_query.iter() {
+                if let Some(contact) = contact(shape, &transform, static_shape, static_transform) {
+                    transform.translation += contact.normal * contact.depth;
+                    remove_velocity_along_normal(&mut body, contact.normal);
+                }
+            }
+        }
+
+        let mut combinations = bodies_query.iter_combinations_mut();
+        while let Some(
+            [(a_entity, mut a_transform, a_shape, mut a_body), (b_entity, mut b_transform, b_shape, mut b_body)],
+        ) = combinations.fetch_next()
+        {
+            let a_simulated = is_simulated(a_entity, &a_body);
+            let b_simulated = is_simulated(b_entity, &b_body);
+            if !a_simulated && !b_simulated {
+                continue;
+            }
+
+            let contact = match contact(a_shape, &a_transform, b_shape, &b_transform) {
+                None => continue,
+                Some(contact) => contact,
+            };
+
+            // not simulated body acts as an obstacle; otherwise both bodies are moved apart equally
+            let a_share = match (a_simulated, b_simulated) {
+                (true, false) => 1.,
+                (false, true) => 0.,
+                _ => 0.5,
+            };
+
+            if a_simulated {
+                a_transform.translation += contact.normal * contact.depth * a_share;
+                remove_velocity_along_normal(&mut a_body, contact.normal);
+            }
+            if b_simulated {
+                b_transform.translation -= contact.normal * contact.depth * (1. - a_share);
+                remove_velocity_along_normal(&mut b_body, -contact.normal);
+            }
+        }
+    }
+}
+
+/// Stops the body from moving further into the surface with the given normal.
+fn remove_velocity_along_normal(body: &mut Body, normal: Vec3) {
+    let velocity_into_surface = body.velocity.dot(normal);
+    if velocity_into_surface < 0. {
+        body.velocity -= normal * velocity_into_surface;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    fn assert_contact(contact: Option<Contact>, normal: Vec3, depth: f32) {
+        let contact = contact.expect("shapes should touch");
+        assert!(
+            contact.normal.abs_diff_eq(normal, 1e-4),
+            "{:?} != {:?}",
+            contact.normal,
+            normal
+        );
+        assert!((contact.depth - depth).abs() < 1e-3, "{} != {}", contact.depth, depth);
+    }
+
+    #[test]
+    fn sphere_touches_box_face() {
+        let sphere = CollisionShape::Sphere { radius: 10. };
+        let cube = CollisionShape::Box {
+            half_extents: Vec3::splat(50.),
+        };
+        let sphere_transform = Transform::from_xyz(5., 55., -10.);
+
+        assert_contact(
+            contact(&sphere, &sphere_transform, &cube, &Transform::IDENTITY),
+            Vec3::Y,
+            5.,
+        );
+        assert_contact(
+            contact(&cube, &Transform::IDENTITY, &sphere, &sphere_transform),
+            -Vec3::Y,
+            5.,
+        );
+        assert!(contact(
+            &sphere,
+            &Transform::from_xyz(5., 61., -10.),
+            &cube,
+            &Transform::IDENTITY
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn capsule_touches_box_edge() {
+        let capsule = CollisionShape::Capsule {
+            radius: 10.,
+            half_height: 20.,
+        };
+        let cube = CollisionShape::Box {
+            half_extents: Vec3::splat(50.),
+        };
+        // the capsule lies along the `Z` axis, parallel to the edge at `x = y = 50`
+        let capsule_transform = Transform::from_xyz(55., 55., 0.).with_rotation(Quat::from_rotation_x(FRAC_PI_2));
+
+        let normal = Vec3::new(1., 1., 0.).normalize();
+        let depth = 10. - 50f32.sqrt();
+        assert_contact(
+            contact(&capsule, &capsule_transform, &cube, &Transform::IDENTITY),
+            normal,
+            depth,
+        );
+    }
+
+    #[test]
+    fn resting_box_is_pushed_onto_surface() {
+        let table = CollisionShape::Box {
+            half_extents: Vec3::new(100., 10., 100.),
+        };
+        let table_transform = Transform::from_xyz(0., -10., 0.);
+        let cube = CollisionShape::Box {
+            half_extents: Vec3::splat(20.),
+        };
+        let mut cube_transform = Transform::from_xyz(30., 19., 0.).with_rotation(Quat::from_rotation_y(0.3));
+
+        let resting_contact = contact(&cube, &cube_transform, &table, &table_transform);
+        assert_contact(resting_contact, Vec3::Y, 1.);
+
+        let resting_contact = resting_contact.unwrap();
+        cube_transform.translation += resting_contact.normal * resting_contact.depth;
+        assert!(contact(&cube, &cube_transform, &table, &table_transform).is_none());
+
+        let mut body = Body {
+            velocity: Vec3::new(100., -500., 0.),
+            sleeping: false,
+        };
+        remove_velocity_along_normal(&mut body, resting_contact.normal);
+        assert_eq!(body.velocity, Vec3::new(100., 0., 0.));
+    }
+}
@@ -0,0 +1,135 @@
+use std::fs;
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
+
+/// Config file, relative to the assets folder.
+pub const CONFIG_PATH: &str = "app.config.ron";
+
+/// Values which can be tuned without recompiling. Changes of the file are applied while the app runs,
+/// except the camera origin and hand models, which are used only at the startup.
+/// Distances are given in scene units (millimeters).
+#[derive(Resource, Deserialize, TypeUuid, Clone, Debug)]
+#[uuid = "3f1e5a4c-7d2b-4e8a-9c61-0b5d2f7a9e13"]
+#[serde(default)]
+pub struct AppConfig {
+    pub window_width: f32,
+    pub window_height: f32,
+    pub table_size: [f32; 3],
+    /// Distance between [`PlayerCamera`](crate::PlayerCamera) and hands.
+    pub hands_distance: f32,
+    pub camera_origin: [f32; 3],
+    /// Maximal distance between a fingertip and the object's center for the finger to hold the object.
+    pub grab_radius: f32,
+    /// Number of fingertips needed to grab an object.
+    pub grab_min_digits: usize,
+    /// Alpha of object's bounds while the object is grabbed.
+    pub grabbed_bounds_alpha: f32,
+    pub released_bounds_alpha: f32,
+    /// Scenes of rigged hand models, e.g. `models/left_hand.glb#Scene0`, shown in the rigged hand style.
+    pub left_hand_model: Option<String>,
+    pub right_hand_model: Option<String>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            window_width: 1920.,
+            window_height: 1080.,
+            table_size: [1600., 700., 1600.],
+            hands_distance: 800.,
+            camera_origin: [0., 350., 500.],
+            grab_radius: 35.,
+            grab_min_digits: 3,
+            grabbed_bounds_alpha: 0.8,
+            released_bounds_alpha: 0.4,
+            left_hand_model: None,
+            right_hand_model: None,
+        }
+    }
+}
+
+impl AppConfig {
+    /// Reads the config directly from the assets folder, so it can be used before the app is built.
+    /// Logging isn't set up at that point, so a missing or invalid file is returned as an error to be logged later.
+    pub fn read() -> Result<Self, String> {
+        let path = format!("assets/{CONFIG_PATH}");
+        fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|ron| ron::from_str::<AppConfig>(&ron).map_err(|err| err.to_string()))
+            .map_err(|err| format!("{path} couldn't be read: {err}"))
+    }
+}
+
+#[derive(Default)]
+struct AppConfigLoader;
+
+impl AssetLoader for AppConfigLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let config = ron::de::from_bytes::<AppConfig>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(config));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["config.ron"]
+    }
+}
+
+/// Keeps the config asset loaded, so its changes are tracked.
+#[derive(Resource)]
+struct AppConfigHandle(#[allow(dead_code)] Handle<AppConfig>);
+
+/// Loads [`AppConfig`] as an asset and copies it into the resource whenever the file changes.
+/// The initial config has to be inserted as a resource beforehand, see [`AppConfig::read`].
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<AppConfig>()
+            .init_asset_loader::<AppConfigLoader>()
+            .add_startup_system(load_config_asset)
+            .add_system(reload_config)
+            .add_system(apply_window_size);
+    }
+}
+
+fn load_config_asset(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(AppConfigHandle(asset_server.load(CONFIG_PATH)));
+}
+
+fn reload_config(
+    mut config: ResMut<AppConfig>,
+    mut config_events: EventReader<AssetEvent<AppConfig>>,
+    configs: Res<Assets<AppConfig>>,
+) {
+    for event in config_events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            if let Some(new_config) = configs.get(handle) {
+                info!("Config reloaded");
+                *config = new_config.clone();
+            }
+        }
+    }
+}
+
+fn apply_window_size(config: Res<AppConfig>, mut windows: ResMut<Windows>) {
+    if !config.is_changed() {
+        return;
+    }
+
+    if let Some(window) = windows.get_primary_mut() {
+        if window.width() != config.window_width || window.height() != config.window_height {
+            window.set_resolution(config.window_width, config.window_height);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+
+use crate::grab_gesture::GrabEvent;
+
+/// Per-object restrictions applied while the object is grabbed.
+/// Snapping is applied only once, when the object is released.
+/// Grabs only translate objects, so rotation isn't limited while grabbing, only snapped on release.
+/// Constraints are reflected, so they can be tuned on the selected object in the editor.
+#[derive(Component, Reflect, FromReflect, Clone, Debug, Default)]
+#[reflect(Component)]
+pub struct MovementConstraints {
+    pub translation_lock: Option<TranslationLock>,
+    pub region: Option<Region>,
+    /// Grid step the translation is rounded to on release.
+    pub translation_snap: Option<f32>,
+    /// Angle increment (in radians) the rotation is rounded to on release.
+    pub rotation_snap: Option<f32>,
+}
+
+#[derive(Reflect, FromReflect, Clone, Copy, Debug)]
+pub enum TranslationLock {
+    /// Object can move only along the given direction.
+    Axis(Vec3),
+    /// Object can move only on the plane with the given normal.
+    Plane(Vec3),
+}
+
+/// Axis aligned box in world space the object's translation is clamped to.
+#[derive(Reflect, FromReflect, Clone, Copy, Debug, PartialEq)]
+pub struct Region {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Region {
+    /// Region above the table top, limited to the table footprint.
+    /// `margin` keeps the object's center away from the edges and the surface.
+    pub fn above_table(table_size: [f32; 3], table_translation: Vec3, margin: f32) -> Self {
+        let half_size = Vec3::from_array(table_size) / 2.;
+        let table_top = table_translation.y + half_size.y;
+
+        Region {
+            min: Vec3::new(
+                table_translation.x - half_size.x + margin,
+                table_top + margin,
+                table_translation.z - half_size.z + margin,
+            ),
+            max: Vec3::new(
+                table_translation.x + half_size.x - margin,
+                f32::INFINITY,
+                table_translation.z + half_size.z - margin,
+            ),
+        }
+    }
+}
+
+impl MovementConstraints {
+    pub fn constrain_translation(&self, start: Vec3, target: Vec3) -> Vec3 {
+        let mut translation = match self.translation_lock {
+            None => target,
+            Some(TranslationLock::Axis(axis)) => {
+                let axis = axis.normalize_or_zero();
+                start + axis * (target - start).dot(axis)
+            }
+            Some(TranslationLock::Plane(normal)) => {
+                let normal = normal.normalize_or_zero();
+                target - normal * (target - start).dot(normal)
+            }
+        };
+
+        if let Some(region) = self.region {
+            translation = translation.clamp(region.min, region.max);
+        }
+
+        translation
+    }
+
+    pub fn snap(&self, transform: &mut Transform) {
+        if let Some(step) = self.translation_snap.filter(|step| *step > 0.) {
+            transform.translation = (transform.translation / step).round() * step;
+
+            // snapping must not push the object outside of the allowed region
+            if let Some(region) = self.region {
+                transform.translation = transform.translation.clamp(region.min, region.max);
+            }
+        }
+
+        if let Some(increment) = self.rotation_snap.filter(|increment| *increment > 0.) {
+            let (y, x, z) = transform.rotation.to_euler(EulerRot::YXZ);
+            let round = |angle: f32| (angle / increment).round() * increment;
+
+            transform.rotation = Quat::from_euler(EulerRot::YXZ, round(y), round(x), round(z));
+        }
+    }
+}
+
+pub fn snap_released_obj(
+    mut grab_events: EventReader<GrabEvent>,
+    mut constrained_query: Query<(&mut Transform, &MovementConstraints)>,
+) {
+    for event in grab_events.iter() {
+        if let GrabEvent::Ended(entity) = event {
+            if let Ok((mut transform, constraints)) = constrained_query.get_mut(*entity) {
+                constraints.snap(&mut transform);
+            }
+        }
+    }
+}
@@ -1,32 +1,108 @@
 use bevy::prelude::*;
 use mac::unwrap_or_return;
 
-use leap_input::leap_controller_plugin::{BoneComponent, BoneType, DigitType};
+use leap_input::hand_query::HandQuery;
+use leap_input::leap_controller_plugin::{BoneComponent, DigitType, MyHandType};
 
-use crate::MainGizmo;
+use crate::config::AppConfig;
+use crate::constraints::MovementConstraints;
+use crate::mouse_grab::MouseDrag;
+use crate::pinch_gesture::pinch_point;
+use crate::prism::{PrismSettings, PrismState};
 
 #[derive(Component)]
 pub struct ObjectBounds;
 
+/// Marks objects which can be grabbed with hands.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Grabbable;
+
+/// Gesture which has to be used to grab the object. Objects without it are grasped.
+#[derive(Component, Reflect, FromReflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Component)]
+pub enum GrabMode {
+    /// At least three fingertips around the object.
+    #[default]
+    Grasp,
+    /// Thumb and index finger pinching near the object; suited for small objects.
+    Pinch,
+}
+
+/// What is holding the grabbed object.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GrabSource {
+    Digits(Vec<(MyHandType, DigitType)>),
+    Pinch(MyHandType),
+    /// Dragging with the mouse, see [`MouseDrag`].
+    Mouse,
+}
+
+impl Default for GrabSource {
+    fn default() -> Self {
+        GrabSource::Digits(Vec::new())
+    }
+}
+
+/// Sent when grabbing of an entity starts or ends.
+pub enum GrabEvent {
+    Started(Entity),
+    Ended(Entity),
+}
+
 #[derive(Clone, Default, Resource)]
 pub struct GrabData {
     current_entity: Option<Entity>,
     previous_entity: Option<Entity>,
     start_hands_transform: Transform,
     start_obj_transform: Transform,
-    digits_involved: Vec<DigitType>,
+    source: GrabSource,
+    /// Other objects moved together with the grabbed one, with their transforms at grab start.
+    group: Vec<(Entity, Transform)>,
 }
 
 impl GrabData {
-    fn clear(&mut self) {
+    pub fn current_entity(&self) -> Option<Entity> {
+        self.current_entity
+    }
+
+    pub fn source(&self) -> &GrabSource {
+        &self.source
+    }
+
+    /// The grabbed entity followed by members of its group.
+    pub fn held_entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.current_entity
+            .into_iter()
+            .chain(self.group.iter().map(|(member, _)| *member))
+    }
+
+    /// Whether the entity is grabbed directly or as a part of the grabbed group.
+    pub fn is_held(&self, entity: Entity) -> bool {
+        self.current_entity == Some(entity) || self.group.iter().any(|(member, _)| *member == entity)
+    }
+
+    pub fn set_group(&mut self, group: Vec<(Entity, Transform)>) {
+        self.group = group;
+    }
+
+    pub fn clear(&mut self) {
         self.previous_entity = self.current_entity;
         self.current_entity = None;
-        self.digits_involved.clear();
+        self.source = GrabSource::default();
+        self.group.clear();
     }
 
-    fn update(&mut self, entity: Entity, start_hands_transform: Transform, start_obj_transform: Transform) {
+    pub fn update(
+        &mut self,
+        entity: Entity,
+        source: GrabSource,
+        start_hands_transform: Transform,
+        start_obj_transform: Transform,
+    ) {
         self.previous_entity = self.current_entity;
         self.current_entity = Some(entity);
+        self.source = source;
         self.start_hands_transform = start_hands_transform;
         self.start_obj_transform = start_obj_transform;
     }
@@ -34,83 +110,189 @@ impl GrabData {
 
 pub fn detect_obj_grabbing(
     mut grab_res: ResMut<GrabData>,
-    main_gizmo_query: Query<(Entity, &Transform), With<MainGizmo>>,
-    digits_query: Query<(&Transform, &BoneComponent)>,
+    mut grab_events: EventWriter<GrabEvent>,
+    config: Res<AppConfig>,
+    grabbable_query: Query<(Entity, &Transform, Option<&GrabMode>), With<Grabbable>>,
+    hand_query: HandQuery,
 ) {
-    let (entity, main_gizmo_transform) = main_gizmo_query.single();
+    let digits_inside_bounds = |obj_transform: &Transform| {
+        hand_query
+            .fingertips()
+            .filter(|(_, _, tip)| tip.distance(obj_transform.translation) < config.grab_radius)
+            .collect::<Vec<_>>()
+    };
 
-    let digits_inside_bounds = digits_query
+    // while grabbing only the grabbed object is checked
+    if let Some(grabbed_entity) = grab_res.current_entity {
+        // pinching and mouse dragging are handled by their own systems
+        if !matches!(grab_res.source, GrabSource::Digits(_)) {
+            return;
+        }
+
+        let (entity, transform, _) = match grabbable_query.get(grabbed_entity) {
+            // the object was despawned; the grab is ended by whoever despawned it
+            Err(_) => return,
+            Ok(grabbed) => grabbed,
+        };
+        let digits = digits_inside_bounds(transform);
+        update_grab_resource(
+            &mut grab_res,
+            &mut grab_events,
+            entity,
+            transform,
+            &digits,
+            config.grab_min_digits,
+        );
+        return;
+    }
+
+    let graspable = grabbable_query
         .iter()
-        .filter(|(_, bone)| bone.bone_type == BoneType::Distal)
-        .filter(|(t, _)| t.translation.distance(main_gizmo_transform.translation) < 35.)
-        .collect::<Vec<_>>();
+        .filter(|(_, _, mode)| !matches!(mode, Some(GrabMode::Pinch)));
 
-    update_grab_resource(&mut grab_res, entity, main_gizmo_transform, &digits_inside_bounds);
+    for (entity, transform, _) in graspable {
+        // held or unreliable hands may keep a grab, but can't start one
+        let digits = digits_inside_bounds(transform)
+            .into_iter()
+            .filter(|(hand_type, _, _)| hand_query.can_interact(*hand_type))
+            .collect::<Vec<_>>();
+        update_grab_resource(
+            &mut grab_res,
+            &mut grab_events,
+            entity,
+            transform,
+            &digits,
+            config.grab_min_digits,
+        );
+
+        if grab_res.current_entity.is_some() {
+            break;
+        }
+    }
 }
 
 fn update_grab_resource(
     grab_res: &mut ResMut<GrabData>,
+    grab_events: &mut EventWriter<GrabEvent>,
     entity: Entity,
-    main_gizmo_transform: &Transform,
-    digits_inside_bounds: &Vec<(&Transform, &BoneComponent)>,
+    obj_transform: &Transform,
+    digits_inside_bounds: &[(MyHandType, DigitType, Vec3)],
+    min_digits: usize,
 ) {
     match grab_res.current_entity {
         None => {
-            if digits_inside_bounds.len() < 3 {
+            if digits_inside_bounds.len() < min_digits {
                 return;
             }
 
             // start new grabbing
             let mut fingers_center = Vec3::ZERO;
-            for (t, b) in digits_inside_bounds {
-                fingers_center += t.translation;
-                grab_res.digits_involved.push(b.digit_type);
+            let mut digits_involved = Vec::new();
+            for (hand_type, digit_type, tip) in digits_inside_bounds {
+                fingers_center += *tip;
+                digits_involved.push((*hand_type, *digit_type));
             }
 
             fingers_center /= digits_inside_bounds.len() as f32;
 
             grab_res.update(
                 entity,
+                GrabSource::Digits(digits_involved),
                 Transform::from_translation(fingers_center),
-                Transform::from_translation(main_gizmo_transform.translation),
+                *obj_transform,
             );
+            grab_events.send(GrabEvent::Started(entity));
         }
-        Some(_) => {
-            if digits_inside_bounds.len() >= 3 {
+        Some(grabbed_entity) => {
+            if digits_inside_bounds.len() >= min_digits {
                 // grabbing in progress
                 return;
             }
 
             // end of a grabbing; clear resource
             grab_res.clear();
+            grab_events.send(GrabEvent::Ended(grabbed_entity));
         }
     }
 }
 
+fn constrained_translation(constraints: Option<&MovementConstraints>, start: Vec3, target: Vec3) -> Vec3 {
+    match constraints {
+        None => target,
+        Some(constraints) => constraints.constrain_translation(start, target),
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 pub fn update_grabbed_obj_transform(
+    time: Res<Time>,
     grab_res: Res<GrabData>,
-    digits_query: Query<(&Transform, &BoneComponent)>,
-    mut transform_query: Query<&mut Transform, (With<MainGizmo>, Without<BoneComponent>)>,
+    prism_settings: Res<PrismSettings>,
+    mut prism_state: ResMut<PrismState>,
+    mouse_drag: Res<MouseDrag>,
+    hand_query: HandQuery,
+    mut transform_query: Query<
+        (&mut Transform, Option<&MovementConstraints>),
+        (With<Grabbable>, Without<BoneComponent>),
+    >,
 ) {
     let grabbed_entity = unwrap_or_return!(grab_res.current_entity, ());
-    let mut grabbed_entity_transform = transform_query.get_mut(grabbed_entity).unwrap();
+    let (mut grabbed_entity_transform, constraints) = match transform_query.get_mut(grabbed_entity) {
+        Err(_) => return,
+        Ok(grabbed) => grabbed,
+    };
 
-    let mut involved_digits_center = Vec3::ZERO;
-    for (t, b) in digits_query.iter() {
-        if b.bone_type == BoneType::Distal && grab_res.digits_involved.contains(&b.digit_type) {
-            involved_digits_center += t.translation;
+    let grip_point = match &grab_res.source {
+        GrabSource::Digits(digits_involved) => {
+            let tips = digits_involved
+                .iter()
+                .filter_map(|(hand_type, digit_type)| hand_query.fingertip(*hand_type, *digit_type))
+                .collect::<Vec<_>>();
+            if tips.is_empty() {
+                return;
+            }
+
+            tips.iter().sum::<Vec3>() / tips.len() as f32
         }
+        GrabSource::Pinch(hand_type) => match hand_query.hand(*hand_type) {
+            // hand is lost; keep the object in place until the pinch detection ends grabbing
+            None => return,
+            Some(hand) => hand_query.world_point(pinch_point(hand)),
+        },
+        GrabSource::Mouse => match mouse_drag.point {
+            None => return,
+            Some(point) => point,
+        },
+    };
+
+    // grab data changes only when a grabbing starts or ends
+    if grab_res.is_changed() {
+        prism_state.reset();
     }
 
-    involved_digits_center /= grab_res.digits_involved.len() as f32;
+    let prism_offset = prism_state.update(&prism_settings, grip_point, time.delta_seconds());
 
-    grabbed_entity_transform.translation = grab_res.start_obj_transform.translation
-        + (involved_digits_center - grab_res.start_hands_transform.translation);
+    let start_translation = grab_res.start_obj_transform.translation;
+    let target_translation =
+        start_translation + (grip_point - grab_res.start_hands_transform.translation) - prism_offset;
+
+    grabbed_entity_transform.translation = constrained_translation(constraints, start_translation, target_translation);
+
+    // group members follow the grabbed object, each within its own constraints
+    let grab_offset = grabbed_entity_transform.translation - start_translation;
+    for (member, member_start_transform) in grab_res.group.iter() {
+        if let Ok((mut member_transform, member_constraints)) = transform_query.get_mut(*member) {
+            let member_start = member_start_transform.translation;
+            member_transform.translation =
+                constrained_translation(member_constraints, member_start, member_start + grab_offset);
+        }
+    }
 }
 
 pub fn update_grabbed_obj_transparency(
     grab_res: Res<GrabData>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    config: Res<AppConfig>,
     // todo: if we could query parent entity directly (or store child entity) then we could use
     // todo: shorter and more performant 'query.get_mut(entity)'
     mut material_query: Query<(&Parent, &Handle<StandardMaterial>), With<ObjectBounds>>,
@@ -126,7 +308,7 @@ pub fn update_grabbed_obj_transparency(
             }
 
             let some_color = &mut materials.get_mut(material_handle).unwrap().base_color;
-            some_color.set_a(0.8);
+            some_color.set_a(config.grabbed_bounds_alpha);
         }
     } else {
         match grab_res.previous_entity {
@@ -139,7 +321,7 @@ pub fn update_grabbed_obj_transparency(
 
                     let some_color = &mut materials.get_mut(material_handle).unwrap().base_color;
                     info!("updating transparency");
-                    some_color.set_a(0.4);
+                    some_color.set_a(config.released_bounds_alpha);
                 }
             }
         }
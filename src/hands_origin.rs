@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+
+use leap_input::leap_controller_plugin::HandsOrigin;
+
+use crate::collision::CollisionShape;
+use crate::config::AppConfig;
+use crate::{PlayerCamera, Table};
+
+const SWITCH_MODE_KEY: KeyCode = KeyCode::H;
+
+/// How long it takes for hands to move to the place given by a newly selected mode.
+const TRANSITION_DURATION: f32 = 0.6;
+
+/// Where the tracking device is placed in the scene, i.e. how [`HandsOrigin`] is positioned.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub enum HandsOriginMode {
+    /// Hands stay at the given place in the world.
+    Fixed(Transform),
+    /// Hands are in front of [`PlayerCamera`] at [`AppConfig::hands_distance`] and move with it.
+    /// `pitch` tilts the device around camera's `X` axis, `offset` is given in the camera space.
+    FollowCamera { pitch: f32, offset: Vec3 },
+    /// Device lies on the table top, in its center. `offset` is given in the world space.
+    Table { offset: Vec3 },
+}
+
+impl Default for HandsOriginMode {
+    fn default() -> Self {
+        HandsOriginMode::Fixed(Transform::IDENTITY)
+    }
+}
+
+impl HandsOriginMode {
+    pub fn follow_camera() -> Self {
+        HandsOriginMode::FollowCamera {
+            pitch: 0.,
+            offset: Vec3::new(0., -300., 0.),
+        }
+    }
+
+    pub fn table() -> Self {
+        HandsOriginMode::Table { offset: Vec3::ZERO }
+    }
+}
+
+pub struct HandsOriginTransition {
+    from: Transform,
+    /// From 0 to 1; there is no transition in progress when it reaches 1.
+    progress: f32,
+}
+
+impl Default for HandsOriginTransition {
+    fn default() -> Self {
+        HandsOriginTransition {
+            from: Transform::IDENTITY,
+            progress: 1.,
+        }
+    }
+}
+
+/// Cycles through fixed, camera and table modes. Switching to the fixed mode keeps hands where they are.
+pub fn switch_hands_origin_mode(
+    keys: Res<Input<KeyCode>>,
+    mut mode: ResMut<HandsOriginMode>,
+    hands_origin_query: Query<&Transform, With<HandsOrigin>>,
+) {
+    if !keys.just_pressed(SWITCH_MODE_KEY) {
+        return;
+    }
+
+    *mode = match *mode {
+        HandsOriginMode::Fixed(_) => HandsOriginMode::follow_camera(),
+        HandsOriginMode::FollowCamera { .. } => HandsOriginMode::table(),
+        HandsOriginMode::Table { .. } => HandsOriginMode::Fixed(*hands_origin_query.single()),
+    };
+    info!("Hands origin mode: {:?}", *mode);
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_hands_origin_transform(
+    time: Res<Time>,
+    config: Res<AppConfig>,
+    mode: Res<HandsOriginMode>,
+    mut transition: Local<HandsOriginTransition>,
+    mut hands_origin_query: Query<&mut Transform, With<HandsOrigin>>,
+    camera_query: Query<&Transform, (With<PlayerCamera>, Without<HandsOrigin>)>,
+    table_query: Query<(&Transform, &CollisionShape), (With<Table>, Without<HandsOrigin>)>,
+) {
+    let mut hands_origin_transform = hands_origin_query.single_mut();
+
+    let target = match *mode {
+        HandsOriginMode::Fixed(transform) => transform,
+        HandsOriginMode::FollowCamera { pitch, offset } => {
+            let camera_transform = camera_query.single();
+            Transform {
+                translation: camera_transform.translation
+                    + camera_transform.forward() * config.hands_distance
+                    + camera_transform.rotation * offset,
+                rotation: camera_transform.rotation * Quat::from_rotation_x(pitch),
+                ..default()
+            }
+        }
+        HandsOriginMode::Table { offset } => {
+            let table_top = match table_query.get_single() {
+                Ok((transform, CollisionShape::Box { half_extents })) => {
+                    transform.translation + Vec3::Y * half_extents.y
+                }
+                _ => Vec3::ZERO,
+            };
+            Transform::from_translation(table_top + offset)
+        }
+    };
+
+    if mode.is_changed() && !mode.is_added() {
+        transition.from = *hands_origin_transform;
+        transition.progress = 0.;
+    }
+
+    if transition.progress >= 1. {
+        *hands_origin_transform = target;
+        return;
+    }
+
+    transition.progress = (transition.progress + time.delta_seconds() / TRANSITION_DURATION).min(1.);
+
+    // smoothstep, so hands neither jump at the start nor at the end of the transition
+    let t = transition.progress * transition.progress * (3. - 2. * transition.progress);
+    *hands_origin_transform = Transform {
+        translation: transition.from.translation.lerp(target.translation, t),
+        rotation: transition.from.rotation.slerp(target.rotation, t),
+        scale: transition.from.scale.lerp(target.scale, t),
+    };
+}
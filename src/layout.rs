@@ -0,0 +1,279 @@
+use std::fs;
+use std::path::Path;
+
+use bevy::ecs::entity::EntityMap;
+use bevy::prelude::shape::{self, Capsule, Icosphere};
+use bevy::prelude::*;
+use bevy::scene::serde::SceneDeserializer;
+use serde::de::DeserializeSeed;
+
+use crate::collision::{Body, CollisionShape};
+use crate::config::AppConfig;
+use crate::constraints::{MovementConstraints, Region, TranslationLock};
+use crate::grab_gesture::{GrabData, GrabEvent, GrabMode, Grabbable, ObjectBounds};
+use crate::mouse_grab::MouseDrag;
+use crate::selection::SelectionState;
+use crate::MainGizmo;
+
+/// File the layout is saved to and loaded from, relative to the working directory.
+pub const LAYOUT_PATH: &str = "assets/scenes/layout.scn.ron";
+
+/// Distance of props' centers from edges of the table top, see [`Region::above_table`].
+pub const TABLE_MARGIN: f32 = 20.;
+
+const SAVE_KEY: KeyCode = KeyCode::F5;
+const LOAD_KEY: KeyCode = KeyCode::F9;
+
+/// Grabbable object placed on the table, which is a part of the saved layout.
+/// Mesh is generated from the object's [`CollisionShape`].
+#[derive(Component, Reflect, FromReflect, Clone, Default)]
+#[reflect(Component)]
+pub struct Prop {
+    pub color: Color,
+}
+
+/// Saves props to [`LAYOUT_PATH`] on `F5` and loads them back on `F9`.
+/// At startup the saved layout is loaded; when there is none, the default props are spawned.
+pub struct LayoutPlugin;
+
+impl Plugin for LayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Prop>()
+            .register_type::<Grabbable>()
+            .register_type::<GrabMode>()
+            .register_type::<MainGizmo>()
+            .register_type::<CollisionShape>()
+            .register_type::<MovementConstraints>()
+            .register_type::<Region>()
+            .register_type::<TranslationLock>()
+            .register_type::<Option<Region>>()
+            .register_type::<Option<TranslationLock>>()
+            .register_type::<Option<f32>>()
+            .add_startup_system(load_layout_or_default)
+            .add_system(handle_layout_keys)
+            .add_system(insert_props_visuals);
+    }
+}
+
+fn load_layout_or_default(world: &mut World) {
+    if !Path::new(LAYOUT_PATH).exists() {
+        spawn_default_props(world);
+        return;
+    }
+
+    if let Err(err) = load_layout(world) {
+        error!("Failed to load layout from {LAYOUT_PATH}: {err}");
+        spawn_default_props(world);
+    }
+}
+
+fn handle_layout_keys(world: &mut World) {
+    let keys = world.resource::<Input<KeyCode>>();
+    let (save, load) = (keys.just_pressed(SAVE_KEY), keys.just_pressed(LOAD_KEY));
+
+    if save {
+        match save_layout(world) {
+            Ok(()) => info!("Layout saved to {LAYOUT_PATH}"),
+            Err(err) => error!("Failed to save layout to {LAYOUT_PATH}: {err}"),
+        }
+    }
+
+    if load {
+        match load_layout(world) {
+            Ok(()) => info!("Layout loaded from {LAYOUT_PATH}"),
+            Err(err) => error!("Failed to load layout from {LAYOUT_PATH}: {err}"),
+        }
+    }
+}
+
+/// Copies components describing props into a separate world, so only they end up in the scene.
+fn save_layout(world: &mut World) -> Result<(), Box<dyn std::error::Error>> {
+    let type_registry = world.resource::<AppTypeRegistry>().clone();
+    let mut layout_world = World::new();
+
+    let mut props_query = world.query_filtered::<(
+        &Transform,
+        &Prop,
+        &CollisionShape,
+        Option<&GrabMode>,
+        Option<&MovementConstraints>,
+        Option<&MainGizmo>,
+    ), With<Grabbable>>();
+
+    for (transform, prop, shape, grab_mode, constraints, main_gizmo) in props_query.iter(world) {
+        let mut entity = layout_world.spawn((*transform, prop.clone(), *shape, Grabbable));
+
+        if let Some(grab_mode) = grab_mode {
+            entity.insert(*grab_mode);
+        }
+        if let Some(constraints) = constraints {
+            entity.insert(constraints.clone());
+        }
+        if main_gizmo.is_some() {
+            entity.insert(MainGizmo);
+        }
+    }
+
+    let ron = DynamicScene::from_world(&layout_world, &type_registry).serialize_ron(&type_registry)?;
+
+    if let Some(dir) = Path::new(LAYOUT_PATH).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(LAYOUT_PATH, ron)?;
+
+    Ok(())
+}
+
+/// Ends the current grab and drops selection gestures, which would refer to despawned props.
+fn release_props(world: &mut World) {
+    let held_entities = world.resource::<GrabData>().held_entities().collect::<Vec<_>>();
+    let mut grab_events = world.resource_mut::<Events<GrabEvent>>();
+    for entity in held_entities {
+        grab_events.send(GrabEvent::Ended(entity));
+    }
+
+    world.resource_mut::<GrabData>().clear();
+    world.resource_mut::<MouseDrag>().point = None;
+    *world.resource_mut::<SelectionState>() = SelectionState::default();
+}
+
+/// Replaces current props with the ones from the saved layout. A grab in progress is ended.
+fn load_layout(world: &mut World) -> Result<(), Box<dyn std::error::Error>> {
+    let ron = fs::read_to_string(LAYOUT_PATH)?;
+    let type_registry = world.resource::<AppTypeRegistry>().clone();
+
+    let scene = {
+        let type_registry = type_registry.read();
+        let mut deserializer = ron::de::Deserializer::from_str(&ron)?;
+
+        SceneDeserializer {
+            type_registry: &type_registry,
+        }
+        .deserialize(&mut deserializer)?
+    };
+
+    release_props(world);
+
+    let props = world
+        .query_filtered::<Entity, With<Prop>>()
+        .iter(world)
+        .collect::<Vec<_>>();
+    for entity in props {
+        world.entity_mut(entity).despawn_recursive();
+    }
+
+    scene.write_to_world(world, &mut EntityMap::default())?;
+
+    Ok(())
+}
+
+fn spawn_default_props(world: &mut World) {
+    let table_size = world.resource::<AppConfig>().table_size;
+    let table_translation = crate::table_transform(table_size).translation;
+    let color = Color::rgb_u8(50, 224, 229);
+    let constraints = MovementConstraints {
+        region: Some(Region::above_table(table_size, table_translation, TABLE_MARGIN)),
+        translation_snap: Some(10.),
+        ..default()
+    };
+
+    let mut spawn_prop = |shape: CollisionShape, translation: Vec3, grab_mode: GrabMode| {
+        world
+            .spawn((
+                Transform::from_translation(translation),
+                Prop { color },
+                shape,
+                grab_mode,
+                constraints.clone(),
+                Grabbable,
+            ))
+            .id()
+    };
+
+    // main gizmo
+    let main_gizmo = spawn_prop(
+        CollisionShape::Sphere { radius: 20. },
+        Vec3::new(100., 250., 0.),
+        GrabMode::Grasp,
+    );
+    spawn_prop(
+        CollisionShape::Box {
+            half_extents: Vec3::splat(20.),
+        },
+        Vec3::new(-100., 250., 0.),
+        GrabMode::Grasp,
+    );
+    spawn_prop(
+        CollisionShape::Capsule {
+            radius: 12.,
+            half_height: 15.,
+        },
+        Vec3::new(0., 250., -100.),
+        GrabMode::Grasp,
+    );
+    // small bead which is too small to be grasped, so it's picked up with a pinch
+    spawn_prop(
+        CollisionShape::Sphere { radius: 8. },
+        Vec3::new(0., 250., 100.),
+        GrabMode::Pinch,
+    );
+
+    world.entity_mut(main_gizmo).insert(MainGizmo);
+}
+
+fn prop_mesh(collision_shape: &CollisionShape) -> Mesh {
+    match *collision_shape {
+        CollisionShape::Sphere { radius } => Mesh::from(Icosphere {
+            radius,
+            subdivisions: if radius < 10. { 8 } else { 12 },
+        }),
+        CollisionShape::Box { half_extents } => {
+            let size = half_extents * 2.;
+            Mesh::from(shape::Box::new(size.x, size.y, size.z))
+        }
+        CollisionShape::Capsule { radius, half_height } => Mesh::from(Capsule {
+            radius,
+            depth: half_height * 2.,
+            ..default()
+        }),
+    }
+}
+
+/// Props are saved without rendering components; they are recreated once a prop is spawned.
+/// Bodies start asleep, so props stay where they were placed.
+fn insert_props_visuals(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    config: Res<AppConfig>,
+    props_query: Query<(Entity, &Prop, &CollisionShape), Added<Prop>>,
+) {
+    for (entity, prop, shape) in props_query.iter() {
+        commands
+            .entity(entity)
+            .insert((
+                meshes.add(prop_mesh(shape)),
+                materials.add(prop.color.into()),
+                GlobalTransform::default(),
+                VisibilityBundle::default(),
+                Body {
+                    sleeping: true,
+                    ..default()
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    PbrBundle {
+                        mesh: meshes.add(Mesh::from(Icosphere {
+                            radius: config.grab_radius,
+                            subdivisions: 12,
+                        })),
+                        material: materials
+                            .add((*Color::rgb_u8(172, 229, 88).set_a(config.released_bounds_alpha)).into()),
+                        ..default()
+                    },
+                    ObjectBounds,
+                ));
+            });
+    }
+}
@@ -1,66 +1,180 @@
+use bevy::asset::AssetPlugin;
 use bevy::diagnostic::{EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin};
 use bevy::prelude::*;
-use bevy::prelude::shape::Icosphere;
-use bevy::render::mesh::shape::Box;
 use bevy_editor_pls::prelude::*;
 
-use leap_input::leap_controller_plugin::{HandsOrigin, LeapControllerPlugin};
+use leap_input::hand_history::HandHistoryPlugin;
+use leap_input::hand_visuals::HandVisualsPlugin;
+use leap_input::leap_controller_plugin::{update_world_hands_data, LeapControllerPlugin, MyHandType};
+use leap_input::rigged_hand::{RiggedHand, RiggedHandPlugin};
+use leap_input::synthetic_hand::SyntheticHandPlugin;
+use leap_input::tracking_diagnostics::TrackingDiagnosticsPlugin;
 
-use crate::grab_gesture::{detect_obj_grabbing, GrabData, ObjectBounds, update_grabbed_obj_transform, update_grabbed_obj_transparency};
+use crate::camera_controller::{update_camera_transform, CameraControllerPlugin, CameraRig};
+#[cfg(not(feature = "physics"))]
+use crate::collision::CollisionPlugin;
+use crate::collision::{CollisionShape, StaticCollider};
+use crate::config::{AppConfig, ConfigPlugin};
+use crate::constraints::{snap_released_obj, MovementConstraints, Region};
+use crate::grab_gesture::{
+    detect_obj_grabbing, GrabData, GrabEvent, update_grabbed_obj_transform, update_grabbed_obj_transparency,
+};
+use crate::hands_origin::{switch_hands_origin_mode, update_hands_origin_transform, HandsOriginMode};
+use crate::layout::{LayoutPlugin, TABLE_MARGIN};
+use crate::mouse_grab::{drag_obj_with_mouse, MouseDrag};
+#[cfg(feature = "physics")]
+use crate::physics::PhysicsPlugin;
+use crate::pinch_gesture::detect_obj_pinching;
+use crate::prism::{PrismSettings, PrismState};
+use crate::selection::{detect_selection_gestures, highlight_selected_objs, SelectionState, update_group_grab};
 
-mod helpers;
+mod camera_controller;
+// with `physics` enabled only shapes are used; they are converted into rapier colliders
+#[cfg_attr(feature = "physics", allow(dead_code))]
+mod collision;
+mod config;
+mod constraints;
 mod grab_gesture;
-
-pub const HEIGHT: f32 = 1080.;
-pub const WIDTH: f32 = 1920.;
-pub const TABLE_SIZE: [f32; 3] = [1600., 700., 1600.];
-pub const HANDS_DISTANCE: f32 = 800.;
-pub const CAMERA_ORIGIN: Transform = Transform::from_xyz(0., 350., 500.);
+mod hands_origin;
+mod layout;
+mod mouse_grab;
+#[cfg(feature = "physics")]
+mod physics;
+mod pinch_gesture;
+mod prism;
+mod selection;
 
 fn main() {
-    App::new()
-        .insert_resource(ClearColor(Color::rgb(0.2, 0.2, 0.2)))
+    let (config, config_error) = match AppConfig::read() {
+        Ok(config) => (config, None),
+        Err(err) => (AppConfig::default(), Some(err)),
+    };
+    let mut app = App::new();
+
+    app.insert_resource(ClearColor(Color::rgb(0.2, 0.2, 0.2)))
         .insert_resource(GrabData::default())
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            window: WindowDescriptor {
-                width: WIDTH,
-                height: HEIGHT,
-                ..default()
-            },
-            ..default()
-        }))
+        .insert_resource(PrismSettings::default())
+        .insert_resource(PrismState::default())
+        .insert_resource(SelectionState::default())
+        .insert_resource(HandsOriginMode::default())
+        .insert_resource(MouseDrag::default())
+        .add_event::<GrabEvent>()
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    window: WindowDescriptor {
+                        width: config.window_width,
+                        height: config.window_height,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .set(AssetPlugin {
+                    watch_for_changes: true,
+                    ..default()
+                }),
+        )
+        .insert_resource(config)
+        .add_plugin(ConfigPlugin)
         .add_plugin(FrameTimeDiagnosticsPlugin::default())
         .add_plugin(EntityCountDiagnosticsPlugin::default())
+        .add_plugin(TrackingDiagnosticsPlugin)
         .add_plugin(EditorPlugin)
-        .add_plugin(LeapControllerPlugin)
+        .add_plugin(LeapControllerPlugin::default())
+        .add_plugin(HandVisualsPlugin)
+        .add_plugin(RiggedHandPlugin)
+        .add_plugin(SyntheticHandPlugin)
+        .add_plugin(HandHistoryPlugin)
+        .add_plugin(LayoutPlugin)
+        .add_plugin(CameraControllerPlugin)
         .add_startup_system(spawn_camera)
         .add_startup_system(spawn_basic_scene)
-        .add_system(adjust_hands_origin_to_camera_transform)
+        .add_startup_system(spawn_rigged_hands)
+        .add_system(switch_hands_origin_mode)
+        .add_system(
+            update_hands_origin_transform
+                .after(switch_hands_origin_mode)
+                .after(update_camera_transform),
+        )
+        .add_system(update_table_size)
         .add_system(detect_obj_grabbing)
+        // gestures read hands after the tracking data of the frame is in
+        .add_system(
+            detect_obj_pinching
+                .after(detect_obj_grabbing)
+                .after(update_world_hands_data),
+        )
+        .add_system(drag_obj_with_mouse.after(detect_obj_pinching))
         .add_system(update_grabbed_obj_transform)
         .add_system(update_grabbed_obj_transparency)
-        .run();
+        .add_system(snap_released_obj.after(detect_obj_grabbing))
+        .add_system(
+            detect_selection_gestures
+                .after(detect_obj_pinching)
+                .after(update_world_hands_data),
+        )
+        .add_system(
+            update_group_grab
+                .after(drag_obj_with_mouse)
+                .before(update_grabbed_obj_transform),
+        )
+        .add_system(highlight_selected_objs);
+
+    #[cfg(feature = "physics")]
+    app.add_plugin(PhysicsPlugin);
+    #[cfg(not(feature = "physics"))]
+    app.add_plugin(CollisionPlugin);
+
+    // logging is set up by the default plugins
+    if let Some(err) = config_error {
+        warn!("Using default config, because {err}");
+    }
+
+    app.run();
 }
 
 /// Main camera. Hands' Transform is calculated in relation to it
 #[derive(Component)]
 pub struct PlayerCamera;
 
-#[derive(Component)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct MainGizmo;
 
+#[derive(Component)]
+pub struct Table;
+
+/// Table's top is at `y = -table_size[1] / 2`.
+fn table_transform(table_size: [f32; 3]) -> Transform {
+    Transform::from_xyz(0., -table_size[1], 0.)
+}
+
+fn table_mesh(table_size: [f32; 3]) -> Mesh {
+    Mesh::from(shape::Box::new(table_size[0], table_size[1], table_size[2]))
+}
+
 fn spawn_basic_scene(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    config: Res<AppConfig>,
 ) {
+    let table_size = config.table_size;
+
     // main table
-    commands.spawn(PbrBundle {
-        mesh: meshes.add(Mesh::from(Box::new(TABLE_SIZE[0], TABLE_SIZE[1], TABLE_SIZE[2]))),
-        transform: Transform::from_xyz(0., -TABLE_SIZE[1], 0.),
-        material: materials.add(Color::rgb_u8(50, 224, 229).into()),
-        ..default()
-    });
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(table_mesh(table_size)),
+            transform: table_transform(table_size),
+            material: materials.add(Color::rgb_u8(50, 224, 229).into()),
+            ..default()
+        },
+        CollisionShape::Box {
+            half_extents: Vec3::from_array(table_size) / 2.,
+        },
+        StaticCollider,
+        Table,
+    ));
 
     commands.spawn(PointLightBundle {
         point_light: PointLight {
@@ -69,70 +183,78 @@ fn spawn_basic_scene(
             shadows_enabled: true,
             ..default()
         },
-        transform: Transform::from_xyz(0., TABLE_SIZE[1] + 200., 0.),
+        transform: Transform::from_xyz(0., table_size[1] + 200., 0.),
         ..default()
     });
+}
+
+/// Resizes the table, and moves regions of props which were limited to the old table top.
+fn update_table_size(
+    mut meshes: ResMut<Assets<Mesh>>,
+    config: Res<AppConfig>,
+    mut table_query: Query<(&mut Transform, &mut Handle<Mesh>, &mut CollisionShape), With<Table>>,
+    mut constraints_query: Query<&mut MovementConstraints>,
+) {
+    if !config.is_changed() {
+        return;
+    }
 
-    // main gizmo
-    commands
-        .spawn((
-            PbrBundle {
-                mesh: meshes.add(Mesh::from(Icosphere {
-                    radius: 20.,
-                    subdivisions: 12,
-                })),
-                transform: Transform::from_xyz(100., 250., 0.),
-                material: materials.add(Color::rgb_u8(50, 224, 229).into()),
-                ..default()
-            },
-            MainGizmo,
-        ))
-        .with_children(|parent| {
-            parent.spawn((
-                PbrBundle {
-                    mesh: meshes.add(Mesh::from(Icosphere {
-                        radius: 35.,
-                        subdivisions: 12,
-                    })),
-                    material: materials.add(Color::rgba_u8(172, 229, 88, 102).into()),
+    let table_size = config.table_size;
+    for (mut transform, mut mesh, mut shape) in table_query.iter_mut() {
+        let half_extents = Vec3::from_array(table_size) / 2.;
+        if *shape == (CollisionShape::Box { half_extents }) {
+            continue;
+        }
+
+        let old_region = match *shape {
+            CollisionShape::Box { half_extents } => Some(Region::above_table(
+                (half_extents * 2.).to_array(),
+                transform.translation,
+                TABLE_MARGIN,
+            )),
+            _ => None,
+        };
+        let new_region = Region::above_table(table_size, table_transform(table_size).translation, TABLE_MARGIN);
+        for mut constraints in constraints_query.iter_mut() {
+            if constraints.region.is_some() && constraints.region == old_region {
+                constraints.region = Some(new_region);
+            }
+        }
+
+        *transform = table_transform(table_size);
+        *mesh = meshes.add(table_mesh(table_size));
+        *shape = CollisionShape::Box { half_extents };
+    }
+}
+
+fn spawn_rigged_hands(mut commands: Commands, asset_server: Res<AssetServer>, config: Res<AppConfig>) {
+    let models = [
+        (MyHandType::Left, &config.left_hand_model),
+        (MyHandType::Right, &config.right_hand_model),
+    ];
+
+    for (hand_type, model) in models {
+        if let Some(model) = model {
+            commands.spawn((
+                SceneBundle {
+                    scene: asset_server.load(model.as_str()),
                     ..default()
                 },
-                ObjectBounds,
+                RiggedHand::new(hand_type),
             ));
-        });
+        }
+    }
 }
 
-fn spawn_camera(mut commands: Commands) {
+fn spawn_camera(mut commands: Commands, config: Res<AppConfig>) {
+    let rig = CameraRig::looking_at(Vec3::from_array(config.camera_origin), Vec3::Y * 200.);
+
     commands.spawn((
         Camera3dBundle {
-            transform: CAMERA_ORIGIN.looking_at(Vec3::Y * 200., Vec3::Y),
+            transform: rig.transform(),
             ..default()
         },
         PlayerCamera,
     ));
-}
-
-/// Sets Transform of [`HandsOrigin`] forward from [`PlayerCamera`].
-/// System also rotates the [`HandsOrigin`].
-///
-/// The distance from the camera is constant, but we can use
-/// ```
-/// translation: camera_transform.translation.lerp(Vec3::ZERO, 0.8),
-/// ```
-/// to adjust distance as percent of length from e.g. center of the scene
-fn adjust_hands_origin_to_camera_transform(
-    mut hand_origin_query: Query<&mut Transform, With<HandsOrigin>>,
-    // camera_query: Query<&Transform, (With<PlayerCamera>, Without<HandsOrigin>)>,
-) {
-    let mut hands_origin_transform = hand_origin_query.single_mut();
-    // let camera_transform = camera_query.single();
-
-    // *hands_origin_transform = Transform {
-    //     translation: camera_transform.translation + camera_transform.forward() * HANDS_DISTANCE,// - Vec3::Y * 300.,
-    //     rotation: Quat::from_axis_angle(Vec3::X, PI),
-    //     // rotation: camera_transform.rotation * Quat::from_euler(EulerRot::YXZ, 0., PI / 4., 0.),
-    //     ..default()
-    // };
-    let transform = Transform::IDENTITY;
-    *hands_origin_transform = transform;
+    commands.insert_resource(rig);
 }
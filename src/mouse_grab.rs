@@ -0,0 +1,117 @@
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::math::Ray;
+use bevy::prelude::*;
+
+use crate::config::AppConfig;
+use crate::grab_gesture::{GrabData, GrabEvent, GrabSource, Grabbable};
+use crate::PlayerCamera;
+
+/// Change of the dragged object's distance from the camera per scroll line.
+const SCROLL_DEPTH_STEP: f32 = 20.;
+
+const MIN_DEPTH: f32 = 50.;
+
+/// Dragging of an object with the mouse, used when there is no tracking device.
+#[derive(Resource, Default)]
+pub struct MouseDrag {
+    /// Cursor position on the drag plane, in the world space. It is the grip point of the mouse grab.
+    pub point: Option<Vec3>,
+    /// Distance of the drag plane from the camera. The plane faces the camera.
+    depth: f32,
+}
+
+/// Distance along the ray to the first intersection with the sphere.
+fn ray_sphere_intersection(ray: &Ray, center: Vec3, radius: f32) -> Option<f32> {
+    let to_center = center - ray.origin;
+    let projection = to_center.dot(ray.direction);
+    let distance_squared = to_center.length_squared() - projection * projection;
+    if distance_squared > radius * radius {
+        return None;
+    }
+
+    let half_chord = (radius * radius - distance_squared).sqrt();
+    [projection - half_chord, projection + half_chord]
+        .into_iter()
+        .find(|distance| *distance >= 0.)
+}
+
+/// Left click on object's bounds grabs it, dragging moves it on a plane facing the camera
+/// and scrolling moves the plane closer or further.
+/// Grabbing goes through [`GrabData`] and [`GrabEvent`]s, just like grabbing with hands.
+#[allow(clippy::too_many_arguments)]
+pub fn drag_obj_with_mouse(
+    mut grab_res: ResMut<GrabData>,
+    mut grab_events: EventWriter<GrabEvent>,
+    mut mouse_drag: ResMut<MouseDrag>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    config: Res<AppConfig>,
+    windows: Res<Windows>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<PlayerCamera>>,
+    grabbable_query: Query<(Entity, &Transform), With<Grabbable>>,
+) {
+    let is_dragging = matches!(grab_res.source(), GrabSource::Mouse);
+    let scroll = mouse_wheel
+        .iter()
+        .map(|wheel| match wheel.unit {
+            MouseScrollUnit::Line => wheel.y,
+            MouseScrollUnit::Pixel => wheel.y / 20.,
+        })
+        .sum::<f32>();
+
+    if is_dragging && !mouse_buttons.pressed(MouseButton::Left) {
+        if let Some(grabbed_entity) = grab_res.current_entity() {
+            grab_res.clear();
+            grab_events.send(GrabEvent::Ended(grabbed_entity));
+        }
+        mouse_drag.point = None;
+        return;
+    }
+
+    let (camera, camera_transform) = camera_query.single();
+    let ray = windows
+        .get_primary()
+        .and_then(|window| window.cursor_position())
+        .and_then(|cursor_position| camera.viewport_to_world(camera_transform, cursor_position));
+    let ray = match ray {
+        // cursor is outside of the window; the object stays where it is
+        None => return,
+        Some(ray) => ray,
+    };
+    let camera_forward = camera_transform.forward();
+
+    if is_dragging {
+        mouse_drag.depth = (mouse_drag.depth + scroll * SCROLL_DEPTH_STEP).max(MIN_DEPTH);
+
+        let distance = mouse_drag.depth / ray.direction.dot(camera_forward);
+        mouse_drag.point = Some(ray.origin + ray.direction * distance);
+        return;
+    }
+
+    if grab_res.current_entity().is_some() || !mouse_buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let nearest_hit = grabbable_query
+        .iter()
+        .filter_map(|(entity, transform)| {
+            ray_sphere_intersection(&ray, transform.translation, config.grab_radius)
+                .map(|distance| (entity, transform, distance))
+        })
+        .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b));
+
+    if let Some((entity, transform, distance)) = nearest_hit {
+        let hit_point = ray.origin + ray.direction * distance;
+
+        mouse_drag.depth = (hit_point - ray.origin).dot(camera_forward);
+        mouse_drag.point = Some(hit_point);
+
+        grab_res.update(
+            entity,
+            GrabSource::Mouse,
+            Transform::from_translation(hit_point),
+            *transform,
+        );
+        grab_events.send(GrabEvent::Started(entity));
+    }
+}
@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use leap_input::leap_controller_plugin::BoneComponent;
+
+use crate::collision::{Body, CollisionShape, StaticCollider, GRAVITY};
+use crate::grab_gesture::{detect_obj_grabbing, GrabEvent};
+
+/// Scene units are millimeters.
+const UNITS_PER_METER: f32 = 1000.;
+
+/// Rapier based simulation of the table, grabbable objects and hands' bones.
+/// Grabbed objects are driven kinematically, released ones are dynamic.
+pub struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(UNITS_PER_METER))
+            .insert_resource(RapierConfiguration {
+                gravity: GRAVITY,
+                ..default()
+            })
+            .add_system(insert_obj_rigid_bodies)
+            .add_system(update_obj_colliders)
+            .add_system(insert_bone_rigid_bodies)
+            .add_system(toggle_hidden_bones_collisions)
+            .add_system(switch_grabbed_obj_rigid_body.after(detect_obj_grabbing));
+    }
+}
+
+impl From<CollisionShape> for Collider {
+    fn from(shape: CollisionShape) -> Self {
+        match shape {
+            CollisionShape::Sphere { radius } => Collider::ball(radius),
+            CollisionShape::Box { half_extents } => Collider::cuboid(half_extents.x, half_extents.y, half_extents.z),
+            CollisionShape::Capsule { radius, half_height } => Collider::capsule_y(half_height, radius),
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn insert_obj_rigid_bodies(
+    mut commands: Commands,
+    shapes_query: Query<
+        (Entity, Option<&Body>, Option<&StaticCollider>),
+        (With<CollisionShape>, Or<(Added<CollisionShape>, Added<Body>)>),
+    >,
+) {
+    for (entity, body, static_collider) in shapes_query.iter() {
+        let mut entity_commands = commands.entity(entity);
+        if static_collider.is_some() {
+            entity_commands.insert(RigidBody::Fixed);
+        } else if let Some(body) = body {
+            entity_commands.insert((
+                RigidBody::Dynamic,
+                Sleeping {
+                    sleeping: body.sleeping,
+                    ..default()
+                },
+            ));
+        }
+    }
+}
+
+/// Colliders are inserted with shapes and replaced when they change, e.g. when the table is resized.
+fn update_obj_colliders(
+    mut commands: Commands,
+    shapes_query: Query<(Entity, &CollisionShape), Changed<CollisionShape>>,
+) {
+    for (entity, shape) in shapes_query.iter() {
+        commands.entity(entity).insert(Collider::from(*shape));
+    }
+}
+
+/// Bones follow tracked hands, so they push other bodies but are never pushed back.
+fn insert_bone_rigid_bodies(mut commands: Commands, bones_query: Query<Entity, Added<BoneComponent>>) {
+    for entity in bones_query.iter() {
+        commands
+            .entity(entity)
+            .insert((RigidBody::KinematicPositionBased, Collider::capsule_y(5., 5.), Sensor));
+    }
+}
+
+/// Bones which are not used by any tracked hand stay at their last position;
+/// they are turned into sensors, so they don't collide with anything.
+fn toggle_hidden_bones_collisions(
+    mut commands: Commands,
+    bones_query: Query<(Entity, &Visibility, Option<&Sensor>), (With<BoneComponent>, Changed<Visibility>)>,
+) {
+    for (entity, visibility, sensor) in bones_query.iter() {
+        match (visibility.is_visible, sensor.is_some()) {
+            (true, true) => {
+                commands.entity(entity).remove::<Sensor>();
+            }
+            (false, false) => {
+                commands.entity(entity).insert(Sensor);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn switch_grabbed_obj_rigid_body(mut commands: Commands, mut grab_events: EventReader<GrabEvent>) {
+    for event in grab_events.iter() {
+        match event {
+            GrabEvent::Started(entity) => {
+                if let Some(mut entity_commands) = commands.get_entity(*entity) {
+                    entity_commands.insert(RigidBody::KinematicPositionBased);
+                }
+            }
+            // grabs of despawned objects end as well, e.g. when the layout is loaded
+            GrabEvent::Ended(entity) => {
+                if let Some(mut entity_commands) = commands.get_entity(*entity) {
+                    entity_commands.insert((RigidBody::Dynamic, Velocity::zero()));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+
+use leap_input::hand_query::HandQuery;
+use leap_input::leap_controller_plugin::MyHand;
+
+use crate::grab_gesture::{GrabData, GrabEvent, GrabMode, GrabSource, Grabbable};
+
+/// Pinch strength above which the pinch grab starts.
+pub const PINCH_START_STRENGTH: f32 = 0.8;
+
+/// Pinch strength below which the pinch grab ends.
+/// It is lower than the start strength, so small changes of the pinch don't drop the object.
+pub const PINCH_END_STRENGTH: f32 = 0.6;
+
+/// Maximal distance between the thumb and index fingertips for the pinch grab to start.
+/// Pinch strength alone also rises when the fingers only curl towards each other.
+pub const PINCH_START_DISTANCE: f32 = 25.;
+
+/// Maximal distance between the pinch point and the object's center.
+const PINCH_RADIUS: f32 = 30.;
+
+/// Point between the thumb and index fingertips, in the same space as the hand.
+pub fn pinch_point(hand: &MyHand) -> Vec3 {
+    let thumb_tip = hand.digits[0].tip();
+    let index_tip = hand.digits[1].tip();
+
+    (thumb_tip + index_tip) / 2.
+}
+
+pub fn detect_obj_pinching(
+    mut grab_res: ResMut<GrabData>,
+    mut grab_events: EventWriter<GrabEvent>,
+    hand_query: HandQuery,
+    grabbable_query: Query<(Entity, &Transform, &GrabMode), With<Grabbable>>,
+) {
+    if let Some(grabbed_entity) = grab_res.current_entity() {
+        let hand_type = match grab_res.source() {
+            GrabSource::Pinch(hand_type) => *hand_type,
+            // object is grabbed with other gesture
+            _ => return,
+        };
+
+        let still_pinching = matches!(
+            hand_query.hand(hand_type),
+            Some(hand) if hand.pinch_strength >= PINCH_END_STRENGTH
+        );
+
+        if !still_pinching {
+            grab_res.clear();
+            grab_events.send(GrabEvent::Ended(grabbed_entity));
+        }
+
+        return;
+    }
+
+    for hand in hand_query.world_hands() {
+        let is_pinching = hand.pinch_strength >= PINCH_START_STRENGTH && hand.pinch_distance <= PINCH_START_DISTANCE;
+        if !is_pinching || !hand_query.can_interact(hand.type_) {
+            continue;
+        }
+
+        let point = pinch_point(&hand);
+        let nearest_obj = grabbable_query
+            .iter()
+            .filter(|(_, _, mode)| **mode == GrabMode::Pinch)
+            .map(|(entity, transform, _)| (entity, transform, transform.translation.distance(point)))
+            .filter(|(_, _, distance)| *distance < PINCH_RADIUS)
+            .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b));
+
+        if let Some((entity, transform, _)) = nearest_obj {
+            // the grip starts in the object's center, so the center is attached at the pinch point,
+            // while constraints and the group still use the object's real start transform
+            grab_res.update(
+                entity,
+                GrabSource::Pinch(hand.type_),
+                Transform::from_translation(transform.translation),
+                *transform,
+            );
+            grab_events.send(GrabEvent::Started(entity));
+            return;
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+
+/// Velocity dependent control-display gain (PRISM) applied to grabbed objects.
+/// Slow hand motion is scaled down, so hand tremor doesn't disturb precise placement.
+/// Speeds are given in scene units (millimeters) per second.
+#[derive(Resource, Clone, Debug)]
+pub struct PrismSettings {
+    pub enabled: bool,
+    /// Hand speed below which the motion is treated as tremor and ignored.
+    pub min_speed: f32,
+    /// Hand speed from which the object follows the hand 1:1.
+    pub scaling_speed: f32,
+    /// Hand speed above which the offset between the hand and the object is recovered.
+    pub recovery_speed: f32,
+    /// Part of the offset recovered per second.
+    pub recovery_rate: f32,
+}
+
+impl Default for PrismSettings {
+    fn default() -> Self {
+        PrismSettings {
+            enabled: true,
+            min_speed: 10.,
+            scaling_speed: 200.,
+            recovery_speed: 300.,
+            recovery_rate: 4.,
+        }
+    }
+}
+
+impl PrismSettings {
+    /// Part of the hand motion applied to the object.
+    pub fn gain(&self, hand_speed: f32) -> f32 {
+        if hand_speed < self.min_speed {
+            return 0.;
+        }
+
+        (hand_speed / self.scaling_speed).min(1.)
+    }
+}
+
+/// Scaling state of the current grab.
+#[derive(Resource, Default)]
+pub struct PrismState {
+    last_grip_point: Option<Vec3>,
+    hand_speed: f32,
+    /// Hand motion which was not applied to the object.
+    offset: Vec3,
+}
+
+impl PrismState {
+    pub fn reset(&mut self) {
+        *self = PrismState::default();
+    }
+
+    /// Returns the offset which has to be subtracted from the 1:1 mapped translation of the object.
+    pub fn update(&mut self, settings: &PrismSettings, grip_point: Vec3, delta_seconds: f32) -> Vec3 {
+        if !settings.enabled {
+            self.reset();
+            return self.offset;
+        }
+        // the motion of a frame without elapsed time is taken into account in the next one
+        if delta_seconds <= 0. {
+            return self.offset;
+        }
+
+        let last_grip_point = self.last_grip_point.replace(grip_point);
+
+        let hand_delta = match last_grip_point {
+            None => return self.offset,
+            Some(last_grip_point) => grip_point - last_grip_point,
+        };
+
+        // tracking frames don't arrive every rendered frame, so the speed is smoothed
+        self.hand_speed = (self.hand_speed + hand_delta.length() / delta_seconds) / 2.;
+
+        self.offset += hand_delta * (1. - settings.gain(self.hand_speed));
+
+        if self.hand_speed > settings.recovery_speed {
+            self.offset *= (1. - settings.recovery_rate * delta_seconds).max(0.);
+        }
+
+        self.offset
+    }
+}
@@ -0,0 +1,226 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use leap_input::hand_query::HandQuery;
+use leap_input::leap_controller_plugin::{MyHand, MyHandType};
+
+use crate::grab_gesture::{GrabData, GrabEvent, Grabbable};
+
+/// Maximal time the index fingertip can stay inside object's bounds to count as a tap.
+const TAP_MAX_DURATION: f64 = 0.3;
+
+const TAP_RADIUS: f32 = 35.;
+
+/// Minimal distance between recorded points of the lasso path.
+const LASSO_POINT_SPACING: f32 = 5.;
+
+const LASSO_MIN_LENGTH: f32 = 150.;
+
+/// Maximal distance between the start and the end of the path for the lasso to be closed.
+const LASSO_CLOSE_DISTANCE: f32 = 40.;
+
+/// How long the open palm has to face up to clear the selection.
+const CLEAR_GESTURE_DURATION: f64 = 0.5;
+
+const SELECTED_EMISSIVE_COLOR: Color = Color::rgb(0.5, 0.3, 0.);
+
+/// Marks selected objects. Grabbing any of them moves the whole selection.
+#[derive(Component)]
+pub struct Selected;
+
+#[derive(Default)]
+struct HandSelectionState {
+    /// Object touched by the index fingertip and the time of the touch.
+    tap: Option<(Entity, f64)>,
+    tap_cancelled: bool,
+    lasso_path: Vec<Vec3>,
+    clear_gesture_start: Option<f64>,
+}
+
+#[derive(Resource, Default)]
+pub struct SelectionState {
+    hands: HashMap<MyHandType, HandSelectionState>,
+    /// Selected objects grabbed together with the grabbed one.
+    held_group: Vec<Entity>,
+}
+
+fn is_pointing(hand: &MyHand) -> bool {
+    hand.digits[1].is_extended && hand.digits[2..].iter().all(|digit| !digit.is_extended)
+}
+
+fn is_open(hand: &MyHand) -> bool {
+    hand.digits.iter().all(|digit| digit.is_extended)
+}
+
+/// Ray casting point-in-polygon test on the `XZ` plane.
+fn is_inside_lasso(lasso_path: &[Vec3], point: Vec3) -> bool {
+    let mut inside = false;
+    let mut previous = lasso_path[lasso_path.len() - 1];
+
+    for current in lasso_path.iter() {
+        if (current.z > point.z) != (previous.z > point.z)
+            && point.x < (previous.x - current.x) * (point.z - current.z) / (previous.z - current.z) + current.x
+        {
+            inside = !inside;
+        }
+        previous = *current;
+    }
+
+    inside
+}
+
+fn path_length(path: &[Vec3]) -> f32 {
+    path.windows(2).map(|points| points[0].distance(points[1])).sum()
+}
+
+/// Detects selection gestures:
+/// - a short tap with the index fingertip toggles selection of the touched object,
+/// - a closed path drawn with the index finger (other fingers curled) selects objects inside it
+///   (when looking from the top),
+/// - an open palm facing up clears the selection.
+#[allow(clippy::too_many_arguments)]
+pub fn detect_selection_gestures(
+    mut commands: Commands,
+    time: Res<Time>,
+    grab_res: Res<GrabData>,
+    hand_query: HandQuery,
+    mut selection_state: ResMut<SelectionState>,
+    grabbable_query: Query<(Entity, &Transform), With<Grabbable>>,
+    selected_query: Query<Entity, With<Selected>>,
+) {
+    let now = time.elapsed_seconds_f64();
+    let is_grabbing = grab_res.current_entity().is_some();
+
+    // hands which can't interact drop their unfinished gestures
+    selection_state
+        .hands
+        .retain(|hand_type, _| hand_query.can_interact(*hand_type));
+
+    for hand in hand_query
+        .world_hands()
+        .filter(|hand| hand_query.can_interact(hand.type_))
+    {
+        let state = selection_state.hands.entry(hand.type_).or_default();
+        let index_tip = hand.digits[1].tip();
+
+        // tap
+        let touched_entity = grabbable_query
+            .iter()
+            .find(|(_, transform)| transform.translation.distance(index_tip) < TAP_RADIUS)
+            .map(|(entity, _)| entity);
+
+        match (state.tap, touched_entity) {
+            (Some((tapped_entity, _)), Some(entity)) if tapped_entity == entity => {
+                state.tap_cancelled |= is_grabbing;
+            }
+            (tap, touched_entity) => {
+                if let Some((tapped_entity, tap_start)) = tap {
+                    if !state.tap_cancelled && now - tap_start <= TAP_MAX_DURATION {
+                        if selected_query.contains(tapped_entity) {
+                            commands.entity(tapped_entity).remove::<Selected>();
+                        } else {
+                            commands.entity(tapped_entity).insert(Selected);
+                        }
+                    }
+                }
+
+                state.tap = touched_entity.map(|entity| (entity, now));
+                state.tap_cancelled = is_grabbing;
+            }
+        }
+
+        // lasso
+        if is_pointing(&hand) && !is_grabbing {
+            let is_far_enough = match state.lasso_path.last() {
+                None => true,
+                Some(last_point) => last_point.distance(index_tip) >= LASSO_POINT_SPACING,
+            };
+
+            if is_far_enough {
+                state.lasso_path.push(index_tip);
+            }
+        } else if !state.lasso_path.is_empty() {
+            let lasso_path = std::mem::take(&mut state.lasso_path);
+            let is_closed = lasso_path[0].distance(lasso_path[lasso_path.len() - 1]) < LASSO_CLOSE_DISTANCE;
+
+            if is_closed && path_length(&lasso_path) >= LASSO_MIN_LENGTH {
+                for (entity, transform) in grabbable_query.iter() {
+                    if is_inside_lasso(&lasso_path, transform.translation) {
+                        commands.entity(entity).insert(Selected);
+                    }
+                }
+            }
+        }
+
+        // clear
+        if is_open(&hand) && hand.palm.normal.y > 0.8 && !is_grabbing {
+            let gesture_start = *state.clear_gesture_start.get_or_insert(now);
+            if now - gesture_start >= CLEAR_GESTURE_DURATION {
+                for entity in selected_query.iter() {
+                    commands.entity(entity).remove::<Selected>();
+                }
+            }
+        } else {
+            state.clear_gesture_start = None;
+        }
+    }
+}
+
+/// When a selected object is grabbed, all other selected objects are grabbed with it.
+pub fn update_group_grab(
+    mut grab_res: ResMut<GrabData>,
+    mut grab_events: EventWriter<GrabEvent>,
+    mut selection_state: ResMut<SelectionState>,
+    selected_query: Query<(Entity, &Transform), With<Selected>>,
+) {
+    let held_group = &mut selection_state.held_group;
+    match grab_res.current_entity() {
+        Some(grabbed_entity) if held_group.is_empty() => {
+            if !selected_query.contains(grabbed_entity) {
+                return;
+            }
+
+            let group = selected_query
+                .iter()
+                .filter(|(entity, _)| *entity != grabbed_entity)
+                .map(|(entity, transform)| (entity, *transform))
+                .collect::<Vec<_>>();
+
+            for (entity, _) in group.iter() {
+                grab_events.send(GrabEvent::Started(*entity));
+                held_group.push(*entity);
+            }
+
+            if !group.is_empty() {
+                grab_res.set_group(group);
+            }
+        }
+        None => {
+            for entity in held_group.drain(..) {
+                grab_events.send(GrabEvent::Ended(entity));
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn highlight_selected_objs(
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    selected_query: Query<&Handle<StandardMaterial>, Added<Selected>>,
+    deselected: RemovedComponents<Selected>,
+    material_query: Query<&Handle<StandardMaterial>, With<Grabbable>>,
+) {
+    for material_handle in selected_query.iter() {
+        if let Some(material) = materials.get_mut(material_handle) {
+            material.emissive = SELECTED_EMISSIVE_COLOR;
+        }
+    }
+
+    for entity in deselected.iter() {
+        if let Ok(material_handle) = material_query.get(entity) {
+            if let Some(material) = materials.get_mut(material_handle) {
+                material.emissive = Color::BLACK;
+            }
+        }
+    }
+}